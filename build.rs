@@ -0,0 +1,48 @@
+fn main() {
+    #[cfg(feature = "embedded-dict")]
+    compress_dictionaries();
+}
+
+/// Compresses the bundled word lists and corrections corpus into the build output directory, from
+/// which they are embedded into the binary. The size and a hash of each are passed on as
+/// environment variables, so that the program can tell them apart without decompressing them.
+#[cfg(feature = "embedded-dict")]
+fn compress_dictionaries() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+
+    for name in ["dict.txt", "suggestion_dict.txt", "corrections.txt"] {
+        println!("cargo:rerun-if-changed={name}");
+
+        let contents = fs::read(name).unwrap();
+        let variable = name.trim_end_matches(".txt").to_uppercase();
+        println!(
+            "cargo:rustc-env=SPELCHEK_{variable}_SIZE={}",
+            contents.len()
+        );
+        println!(
+            "cargo:rustc-env=SPELCHEK_{variable}_HASH={}",
+            fnv_hash(&contents)
+        );
+
+        let out_file = File::create(Path::new(&out_dir).join(format!("{name}.gz"))).unwrap();
+        let mut encoder = GzEncoder::new(out_file, Compression::best());
+        std::io::Write::write_all(&mut encoder, &contents).unwrap();
+        encoder.finish().unwrap();
+    }
+}
+
+/// FNV-1a hash of the bytes
+#[cfg(feature = "embedded-dict")]
+fn fnv_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
@@ -1,10 +1,15 @@
 use anyhow::anyhow;
 use ratatui::widgets::ListState;
 
+use crate::patch::{Patch, PatchTarget};
 use crate::prelude::*;
-use crate::spellchecker::{Misspelling, Spellchecker};
+use crate::spellchecker::dictionary::WordStatus;
+use crate::spellchecker::layers::LayerKind;
+use crate::spellchecker::tokenizer::{PlainText, Tokenizer, TokenizerRegistry};
+use crate::spellchecker::{Misspelling, MisspellingKind, Spellchecker};
 use std::fs::File;
 use std::io::Write;
+use std::sync::Arc;
 use std::{fs, fs::canonicalize, path::PathBuf};
 
 #[derive(Debug)]
@@ -16,16 +21,36 @@ pub enum Screen {
     Quit,
 }
 
+/// One of the files opened in the app. The contents of the file being checked are held by the
+/// [`AppState`] itself.
+#[derive(Debug)]
+struct OpenFile {
+    path: PathBuf,
+    buffer: String,
+    original: String, // The contents the file was opened with
+    tokenizer: Arc<dyn Tokenizer>,
+    misspellings: usize, // How many misspellings were found the last time the file was checked
+    modified: bool,      // Whether the file has corrections which aren't saved
+}
+
 #[derive(Debug)]
 pub struct AppState {
     file_path: PathBuf,
     file_buffer: String,
+    modified: bool,       // Whether the buffer has corrections which aren't saved
+    files: Vec<OpenFile>, // All of the opened files, in the order they are stepped through
+    current_file: usize,  // The index of the file being checked in `files`
     quit_flag: bool,
+    pub tokenizer: Arc<dyn Tokenizer>, // Decides which parts of the file are checked
     pub active_screen: Screen,
     pub selected_misspelling: Option<usize>,
     pub selected_suggestion: Option<usize>,
     pub misspellings_list_state: ListState,
     pub spellchecker: Spellchecker,
+    pub status: Option<String>, // The outcome of the last action, shown at the bottom
+    // The text to write to stdout on exit, when the text was read from stdin rather than a file
+    stdout_text: Option<String>,
+    patch: Option<Patch>, // The saved corrections, when they are kept as a diff
 }
 
 impl Default for AppState {
@@ -33,20 +58,48 @@ impl Default for AppState {
         AppState {
             file_path: PathBuf::new(),
             file_buffer: String::new(),
+            modified: false,
+            files: Vec::new(),
+            current_file: 0,
             quit_flag: false,
+            tokenizer: Arc::new(PlainText),
             active_screen: Screen::Main,
             selected_misspelling: None,
             selected_suggestion: None,
             misspellings_list_state: ListState::default(),
             spellchecker: Spellchecker::default(),
+            status: None,
+            stdout_text: None,
+            patch: None,
         }
     }
 }
 
 impl AppState {
     pub fn new(file_path: PathBuf, file_buffer: String) -> Result<Self> {
-        let file_path = canonicalize(file_path).unwrap(); // make sure that it's the full path
+        AppState::with_spellchecker(file_path, file_buffer, Spellchecker::new()?)
+    }
+
+    pub fn with_spellchecker(
+        file_path: PathBuf,
+        file_buffer: String,
+        spellchecker: Spellchecker,
+    ) -> Result<Self> {
+        // make sure that it's the full path, unless the text doesn't come from a file
+        let file_path = canonicalize(&file_path).unwrap_or(file_path);
+        let tokenizer = TokenizerRegistry::default().for_path(&file_path);
         Ok(Self {
+            files: vec![OpenFile {
+                path: file_path.clone(),
+                buffer: String::new(),
+                original: file_buffer.clone(),
+                tokenizer: tokenizer.clone(),
+                misspellings: 0,
+                modified: false,
+            }],
+            current_file: 0,
+            modified: false,
+            tokenizer,
             file_path,
             file_buffer,
             quit_flag: false,
@@ -54,10 +107,35 @@ impl AppState {
             selected_misspelling: None,
             selected_suggestion: None,
             misspellings_list_state: ListState::default(),
-            spellchecker: Spellchecker::new()?,
+            spellchecker,
+            status: None,
+            stdout_text: None,
+            patch: None,
         })
     }
 
+    /// Makes saving keep the buffer to be written to stdout on exit instead of writing the file,
+    /// for text read from stdin. Until it is saved, the original text is written out.
+    pub fn save_to_stdout(&mut self) {
+        self.stdout_text = Some(self.file_buffer.clone());
+    }
+
+    /// Takes the text to write to stdout on exit, if the text was read from stdin.
+    pub fn take_stdout_text(&mut self) -> Option<String> {
+        self.stdout_text.take()
+    }
+
+    /// Makes saving keep the corrections as a diff against the original contents of the files,
+    /// instead of writing the files.
+    pub fn save_to_patch(&mut self, target: PatchTarget) {
+        self.patch = Some(Patch::new(target));
+    }
+
+    /// Takes the diff of the saved corrections, if they are kept as one.
+    pub fn take_patch(&mut self) -> Option<Patch> {
+        self.patch.take()
+    }
+
     pub fn write_buffer(&self) -> Result<()> {
         fs::write(&self.file_path, &self.file_buffer)?;
         Ok(())
@@ -86,7 +164,101 @@ impl AppState {
     }
 
     pub fn check_spelling(&mut self) {
-        self.spellchecker.check(&self.file_buffer);
+        self.spellchecker.misspellings.clear();
+        self.spellchecker
+            .check_with(&self.file_buffer, self.tokenizer.as_ref());
+    }
+
+    /// Opens another file to be stepped through after the ones already opened, with the number of
+    /// misspellings already found in it.
+    pub fn add_file(
+        &mut self,
+        path: PathBuf,
+        buffer: String,
+        tokenizer: Arc<dyn Tokenizer>,
+        misspellings: usize,
+    ) {
+        self.files.push(OpenFile {
+            path,
+            original: buffer.clone(),
+            buffer,
+            tokenizer,
+            misspellings,
+            modified: false,
+        });
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// The index of the file being checked among the opened files
+    pub fn current_file(&self) -> usize {
+        self.current_file
+    }
+
+    pub fn get_file_path(&self) -> &PathBuf {
+        &self.file_path
+    }
+
+    /// Returns the number of misspellings in each of the opened files
+    pub fn misspelling_counts(&self) -> Vec<usize> {
+        let mut counts: Vec<usize> = self.files.iter().map(|file| file.misspellings).collect();
+        if let Some(count) = counts.get_mut(self.current_file) {
+            *count = self.spellchecker.misspellings.len();
+        }
+        counts
+    }
+
+    /// Switches to checking another of the opened files, keeping the corrections made in the
+    /// current one. If the word list of the other file's project can't be read, the current file
+    /// stays selected.
+    pub fn select_file(&mut self, index: usize) -> Result<()> {
+        if index == self.current_file || index >= self.files.len() {
+            return Ok(());
+        }
+        self.spellchecker
+            .load_project_words(&self.files[index].path)?;
+
+        let current = &mut self.files[self.current_file];
+        current.buffer = std::mem::take(&mut self.file_buffer);
+        current.tokenizer = self.tokenizer.clone();
+        current.misspellings = self.spellchecker.misspellings.len();
+        current.modified = self.modified;
+
+        let next = &mut self.files[index];
+        self.file_path = next.path.clone();
+        self.file_buffer = std::mem::take(&mut next.buffer);
+        self.tokenizer = next.tokenizer.clone();
+        self.modified = next.modified;
+        self.current_file = index;
+
+        // The file is checked again, as words may have been added to the dictionaries since
+        self.check_spelling();
+        self.selected_misspelling = None;
+        self.selected_suggestion = None;
+        self.set_misspellings_list_state();
+
+        let counts = self.misspelling_counts();
+        self.status = Some(format!(
+            "{}: {} misspellings ({} in all {} files)",
+            self.file_path.display(),
+            counts[index],
+            counts.iter().sum::<usize>(),
+            counts.len()
+        ));
+        Ok(())
+    }
+
+    /// Switches to the next opened file, wrapping around after the last one
+    pub fn select_next_file(&mut self) -> Result<()> {
+        self.select_file((self.current_file + 1) % self.files.len().max(1))
+    }
+
+    /// Switches to the previous opened file, wrapping around before the first one
+    pub fn select_previous_file(&mut self) -> Result<()> {
+        let count = self.files.len().max(1);
+        self.select_file((self.current_file + count - 1) % count)
     }
 
     fn is_misspelling_selected(&self) -> bool {
@@ -240,8 +412,85 @@ impl AppState {
             .map(|misspelling| misspelling.get_word().clone())
     }
 
+    /// Describes why the selected misspelling was flagged, and which dictionary layer knows the
+    /// word in a different casing, if any.
+    pub fn describe_selected_misspelling(&self) -> Option<String> {
+        let misspelling = self.get_selected_misspelling()?;
+        let word = misspelling.get_word();
+
+        let description = match misspelling.get_kind() {
+            MisspellingKind::Forbidden => format!("'{word}' is forbidden by the style guide"),
+            MisspellingKind::Replacement => format!(
+                "the style guide prefers '{}' over '{word}'",
+                misspelling.get_suggestions().join("', '")
+            ),
+            MisspellingKind::Capitalization => {
+                let checker = self.spellchecker.checker_for(misspelling);
+                match checker.dictionaries().lookup(word) {
+                    (WordStatus::WrongCase(forms), Some(layer)) => format!(
+                        "'{word}' is written '{}' in the {layer} dictionary",
+                        forms.join("' or '")
+                    ),
+                    _ => format!("'{word}' is written in the wrong case"),
+                }
+            }
+            MisspellingKind::Spelling => format!("'{word}' isn't in any dictionary"),
+        };
+        Some(description)
+    }
+
+    /// Returns the dictionary layer which accepts the word, if any
+    pub fn layer_accepting(&self, word: &str) -> Option<LayerKind> {
+        match self.spellchecker.dictionaries().lookup(word) {
+            (WordStatus::Correct, layer) => layer,
+            _ => None,
+        }
+    }
+
+    /// Adds the selected misspelled word to a dictionary layer, and reports the outcome in the
+    /// status.
+    pub fn add_selected_word(&mut self, layer: LayerKind) {
+        let word = match self.get_misspelled_word() {
+            Some(word) => word,
+            None => return,
+        };
+
+        self.status = match self.spellchecker.add_word(layer, &word) {
+            Ok(()) => {
+                let path = self
+                    .spellchecker
+                    .dictionaries()
+                    .layer(layer)
+                    .and_then(|layer| layer.path.as_ref());
+                Some(match path {
+                    Some(path) => format!(
+                        "added '{word}' to the {layer} dictionary ({})",
+                        path.display()
+                    ),
+                    None => format!("added '{word}' to the {layer} dictionary"),
+                })
+            }
+            Err(error) => Some(format!("error: {error:#}")),
+        };
+
+        self.selected_suggestion = None;
+        self.selected_misspelling_inbound(self.spellchecker.misspellings.len());
+        self.set_misspellings_list_state();
+    }
+
     /// Accepts the currently selected suggestion for the currently selected misspelling.
     pub fn accept_suggestion(&mut self) {
+        self.correct_selected_misspelling(true);
+    }
+
+    /// Replaces the selected misspelling with the selected suggestion like
+    /// [`AppState::accept_suggestion`], but without remembering the correction in the history,
+    /// for corrections that weren't picked by the user.
+    pub fn apply_suggestion(&mut self) {
+        self.correct_selected_misspelling(false);
+    }
+
+    fn correct_selected_misspelling(&mut self, record: bool) {
         // If there is no selected misspelling or suggestion, do nothing.
         if self.selected_misspelling.is_none() || self.selected_suggestion.is_none() {
             return;
@@ -260,13 +509,23 @@ impl AppState {
             .remove(selected_misspelling_idx);
 
         // The suggestion to be put in place of the misspelled word
-        let mut suggestion: String = selected_misspelling
+        let suggestion: String = selected_misspelling
             .get_suggestions()
             .get(self.selected_suggestion.unwrap())
             .unwrap()
             .to_string();
-        match_case(selected_misspelling.get_word(), &mut suggestion); // Match the case of the
-                                                                      // corrected word to the previously misspelled word
+
+        // Remember the correction of the typo, so that it is suggested first the next time
+        if record && selected_misspelling.get_kind() == MisspellingKind::Spelling {
+            if let Err(error) = self
+                .spellchecker
+                .record_correction(selected_misspelling.get_word(), &suggestion)
+            {
+                self.status = Some(format!("error: {error:#}"));
+            }
+        }
+
+        let suggestion = correction_for(selected_misspelling.get_word(), &suggestion);
 
         let misspelling_len: usize =
             selected_misspelling.get_end() - selected_misspelling.get_start() + 1;
@@ -282,6 +541,8 @@ impl AppState {
         self.file_buffer.push_str(&buffer_after[misspelling_len..]); // Adds the
                                                                      // rest of the text to the end of the buffer
 
+        self.modified = true;
+
         self.spellchecker
             .offset_misspelling_positions(len_delta, selected_misspelling_idx);
 
@@ -291,8 +552,21 @@ impl AppState {
         self.set_misspellings_list_state();
     }
 
-    /// Saves the corrected texts by replacing the file contents with the contents of the buffer.
-    pub fn save_file(&self) -> Result<()> {
+    /// Saves the corrected texts by replacing the file contents with the contents of the buffer,
+    /// by keeping them for stdout if the text was read from stdin, or by adding them to the diff.
+    pub fn save_file(&mut self) -> Result<()> {
+        if let Some(text) = &mut self.stdout_text {
+            text.clone_from(&self.file_buffer);
+            self.modified = false;
+            return Ok(());
+        }
+        if let Some(patch) = &mut self.patch {
+            let original = &self.files[self.current_file].original;
+            patch.record(&self.file_path, original, &self.file_buffer);
+            patch.save()?;
+            self.modified = false;
+            return Ok(());
+        }
         if !self.file_path.exists() {
             return Err(anyhow!("opened file doesn't exist and can't be written to"));
         }
@@ -301,10 +575,42 @@ impl AppState {
 
         let buf_bytes: Vec<u8> = self.file_buffer.bytes().collect();
         file.write_all(&buf_bytes)?;
+        self.modified = false;
+        Ok(())
+    }
+
+    /// Saves all of the opened files which have unsaved corrections
+    pub fn save_all_files(&mut self) -> Result<()> {
+        if self.modified {
+            self.save_file()?;
+        }
+        for (i, file) in self.files.iter_mut().enumerate() {
+            if i != self.current_file && file.modified {
+                match &mut self.patch {
+                    Some(patch) => patch.record(&file.path, &file.original, &file.buffer),
+                    None => fs::write(&file.path, &file.buffer)?,
+                }
+                file.modified = false;
+            }
+        }
+        if let Some(patch) = &self.patch {
+            patch.save()?;
+        }
         Ok(())
     }
 }
 
+/// Returns the text replacing the misspelled word when the suggestion is accepted: the suggestion
+/// in the case of the misspelled word, unless it has a canonical casing of its own (like a proper
+/// noun or an acronym).
+pub fn correction_for(misspelled: &str, suggestion: &str) -> String {
+    let mut correction = suggestion.to_string();
+    if !suggestion.chars().any(char::is_uppercase) {
+        match_case(misspelled, &mut correction);
+    }
+    correction
+}
+
 /// Tries to match case of `target` to that of `source`. It does so by matching the case of
 /// individual characters. For each index in `source`, if that index also exists in `target` it
 /// sets the case of the character on that index in `target` to be the same as the character on that
@@ -346,11 +652,21 @@ fn match_case(source: &str, target: &mut String) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::spellchecker::dictionary::Dictionary;
+    use crate::test_support::{bundled_spellchecker, test_dir};
+    use std::path::Path;
+
+    /// Creates the app state with the repository's dictionary, without the user's word list and
+    /// history
+    fn app_state_for(text: &str) -> AppState {
+        AppState::with_spellchecker(PathBuf::from("/"), text.to_string(), bundled_spellchecker())
+            .unwrap()
+    }
 
     #[test]
     fn test_accepting_suggestion() {
         let text = "Hello world, thsi is some example text.";
-        let mut app_state = AppState::new(PathBuf::from("/"), text.to_string()).unwrap();
+        let mut app_state = app_state_for(text);
         app_state.check_spelling();
         app_state.select_first_misspelling();
         app_state.suggest_selected();
@@ -375,7 +691,7 @@ mod tests {
     // The corrected misspelling is the last word
     fn test_accepting_suggestion_last_word() {
         let text = "This piece of text ends with a mispeling";
-        let mut app_state = AppState::new(PathBuf::from("/"), text.to_string()).unwrap();
+        let mut app_state = app_state_for(text);
         app_state.check_spelling();
         app_state.select_first_misspelling();
         app_state.suggest_selected();
@@ -396,10 +712,37 @@ mod tests {
         );
     }
 
+    #[test]
+    // The misspellings are found and corrected by byte offsets, after multibyte characters too
+    fn test_accepting_suggestion_after_multibyte() {
+        let text = "“Quoted” thsi and thta";
+        let mut app_state = app_state_for(text);
+        app_state.check_spelling();
+        app_state.select_first_misspelling();
+        app_state.suggest_selected();
+        app_state.select_next_suggestion();
+
+        let suggestion = app_state
+            .get_suggestions()
+            .unwrap()
+            .get(app_state.selected_suggestion.unwrap())
+            .unwrap()
+            .clone();
+        app_state.accept_suggestion();
+
+        assert_eq!(
+            app_state.file_buffer,
+            format!("“Quoted” {} and thta", suggestion)
+        );
+        let misspelling = app_state.spellchecker.misspellings().last().unwrap();
+        let (start, end) = misspelling.get_range();
+        assert_eq!(&app_state.file_buffer[start..=end], "thta");
+    }
+
     #[test]
     fn test_accepting_suggestion_no_misspelling() {
         let text = "Hello world";
-        let mut app_state = AppState::new(PathBuf::from("/"), text.to_string()).unwrap();
+        let mut app_state = app_state_for(text);
         app_state.accept_suggestion();
         app_state.accept_suggestion();
         app_state.accept_suggestion();
@@ -412,7 +755,7 @@ mod tests {
     #[test]
     fn test_accepting_suggestion_match_case() {
         let text = "HeLllO world, this is some example text.";
-        let mut app_state = AppState::new(PathBuf::from("/"), text.to_string()).unwrap();
+        let mut app_state = app_state_for(text);
         app_state.check_spelling();
         app_state.select_first_misspelling();
         app_state.suggest_selected();
@@ -428,6 +771,193 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_accepting_canonical_case() {
+        let dict = Dictionary::from_sources("NASA\nat\nworks\n", "").unwrap();
+        let text = "Works at Nasa";
+        let mut app_state = AppState::with_spellchecker(
+            PathBuf::from("/"),
+            text.to_string(),
+            Spellchecker::with_dictionary(dict),
+        )
+        .unwrap();
+        app_state.check_spelling();
+        app_state.select_first_misspelling();
+        app_state.suggest_selected();
+        app_state.select_next_suggestion();
+        app_state.accept_suggestion();
+
+        assert_eq!(app_state.file_buffer, "Works at NASA");
+    }
+
+    #[test]
+    fn test_adding_word_to_session() {
+        let dict = Dictionary::from_sources("is\nname\nmy\n", "").unwrap();
+        let text = "My name is Zbigniew";
+        let mut app_state = AppState::with_spellchecker(
+            PathBuf::from("/"),
+            text.to_string(),
+            Spellchecker::with_dictionary(dict),
+        )
+        .unwrap();
+        app_state.check_spelling();
+        app_state.select_first_misspelling();
+        assert_eq!(
+            app_state.describe_selected_misspelling().unwrap(),
+            "'Zbigniew' isn't in any dictionary"
+        );
+
+        app_state.add_selected_word(LayerKind::Session);
+        assert!(app_state.spellchecker.misspellings().is_empty());
+        assert_eq!(app_state.selected_misspelling, None);
+        assert_eq!(
+            app_state.status.as_deref(),
+            Some("added 'Zbigniew' to the session dictionary")
+        );
+        assert_eq!(
+            app_state.layer_accepting("Zbigniew"),
+            Some(LayerKind::Session)
+        );
+    }
+
+    #[test]
+    fn test_recording_accepted_corrections() {
+        let text = "Thsi is it";
+        let mut app_state = app_state_for(text);
+        app_state.check_spelling();
+        app_state.select_first_misspelling();
+        app_state.suggest_selected();
+        app_state.select_next_suggestion();
+        app_state.select_next_suggestion();
+        let accepted = app_state.get_suggestions().unwrap()[1].clone();
+        app_state.accept_suggestion();
+
+        // The second suggestion now comes first
+        let mut app_state_again = AppState::with_spellchecker(
+            PathBuf::from("/"),
+            text.to_string(),
+            std::mem::take(&mut app_state.spellchecker),
+        )
+        .unwrap();
+        app_state_again.check_spelling();
+        app_state_again.select_first_misspelling();
+        app_state_again.suggest_selected();
+        assert_eq!(app_state_again.get_suggestions().unwrap()[0], accepted);
+    }
+
+    #[test]
+    fn test_stepping_through_files() {
+        let mut app_state = app_state_for("Thsi is it");
+        app_state.check_spelling();
+        app_state.add_file(
+            PathBuf::from("/second"),
+            "Good text".to_string(),
+            Arc::new(PlainText),
+            0,
+        );
+        assert_eq!(app_state.misspelling_counts(), [1, 0]);
+
+        app_state.select_first_misspelling();
+        app_state.suggest_selected();
+        app_state.select_next_suggestion();
+        app_state.accept_suggestion();
+        let corrected = app_state.get_buffer().clone();
+
+        app_state.select_next_file().unwrap();
+        assert_eq!(app_state.get_buffer(), "Good text");
+        assert_eq!(app_state.get_file_path(), &PathBuf::from("/second"));
+        assert_eq!(app_state.selected_misspelling, None);
+
+        // The corrections are kept, and the selection wraps around
+        app_state.select_next_file().unwrap();
+        assert_eq!(app_state.current_file(), 0);
+        assert_eq!(app_state.get_buffer(), &corrected);
+        assert_eq!(app_state.misspelling_counts(), [0, 0]);
+    }
+
+    #[test]
+    fn test_switching_to_unreadable_project() {
+        // The project word list of the second file can't be read, as it is a directory
+        let dir = test_dir("unreadable-project");
+        fs::create_dir_all(dir.join(crate::paths::PROJECT_WORDS_FILE_NAME)).unwrap();
+
+        let mut app_state = app_state_for("Thsi is it");
+        app_state.check_spelling();
+        app_state.add_file(
+            dir.join("notes.txt"),
+            "Good text".to_string(),
+            Arc::new(PlainText),
+            0,
+        );
+
+        // The first file stays selected
+        assert!(app_state.select_next_file().is_err());
+        assert_eq!(app_state.current_file(), 0);
+        assert_eq!(app_state.get_file_path(), &PathBuf::from("/"));
+        assert_eq!(app_state.get_buffer(), "Thsi is it");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_saving_to_patch() {
+        let patch_path =
+            std::env::temp_dir().join(format!("spelchek-test-patch-{}.patch", std::process::id()));
+        let mut app_state = app_state_for("Thsi is it");
+        app_state.add_file(
+            PathBuf::from("/second"),
+            "Sme text".to_string(),
+            Arc::new(PlainText),
+            1,
+        );
+        app_state.save_to_patch(PatchTarget::File(patch_path.clone()));
+
+        let mut corrected = Vec::new();
+        for _ in 0..2 {
+            app_state.check_spelling();
+            app_state.select_first_misspelling();
+            app_state.suggest_selected();
+            app_state.select_next_suggestion();
+            app_state.accept_suggestion();
+            corrected.push(app_state.get_buffer().clone());
+            app_state.select_next_file().unwrap();
+        }
+        app_state.select_next_file().unwrap();
+        app_state.save_all_files().unwrap();
+
+        // Neither "/" nor "/second" is written, only the patch
+        let expected = crate::patch::unified_diff(Path::new("/"), "Thsi is it", &corrected[0])
+            + &crate::patch::unified_diff(Path::new("/second"), "Sme text", &corrected[1]);
+        assert_eq!(fs::read_to_string(&patch_path).unwrap(), expected);
+        assert!(!app_state.take_patch().unwrap().is_empty());
+        fs::remove_file(patch_path).unwrap();
+    }
+
+    #[test]
+    fn test_unsaved_text_to_stdout() {
+        let mut app_state = app_state_for("Some text");
+        app_state.save_to_stdout();
+        assert_eq!(app_state.take_stdout_text().unwrap(), "Some text");
+    }
+
+    #[test]
+    fn test_saving_to_stdout() {
+        let text = "Hello world, thsi is it.";
+        let mut app_state = app_state_for(text);
+        app_state.save_to_stdout();
+        app_state.check_spelling();
+        app_state.select_first_misspelling();
+        app_state.suggest_selected();
+        app_state.select_next_suggestion();
+        app_state.accept_suggestion();
+        let corrected = app_state.get_buffer().clone();
+
+        // The file path is "/", but nothing is written to it
+        app_state.save_file().unwrap();
+        assert_ne!(corrected, text);
+        assert_eq!(app_state.take_stdout_text(), Some(corrected));
+        assert_eq!(app_state.take_stdout_text(), None);
+    }
+
     #[test]
     fn test_match_case() {
         let mut target = String::from("hello");
@@ -0,0 +1,758 @@
+//! The `dict` subcommand, which manages the dictionaries installed in the data directory.
+//!
+//! ```text
+//! spelchek dict list
+//! spelchek dict install <file-or-dir-or-archive> [--name <name>] [--language <language>]
+//! spelchek dict remove <name>
+//! spelchek dict info [name]
+//! spelchek dict verify [name]
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+
+use crate::paths::{
+    self, dictionary_dir, Locations, DEFAULT_DICTIONARY, DICT_FILE_NAME, FORBIDDEN_FILE_NAME,
+    LANGUAGE_FILE_NAME, PHRASES_FILE_NAME, REPLACEMENTS_FILE_NAME, SUGGESTION_DICT_FILE_NAME,
+};
+use crate::prelude::*;
+use crate::spellchecker::dictionary::Dictionary;
+use crate::spellchecker::embedded;
+use crate::spellchecker::style::StyleRules;
+
+/// The maximum number of problems printed for a single file by `verify`
+const MAX_REPORTED_PROBLEMS: usize = 20;
+
+#[derive(Debug, PartialEq)]
+pub enum DictCommand {
+    /// List the installed dictionaries
+    List,
+    /// Install a dictionary from a word list, a directory or a `.tar`/`.tar.gz` archive
+    Install {
+        source: PathBuf,
+        name: Option<String>,
+        language: Option<String>,
+    },
+    /// Remove a dictionary from the user's data directory
+    Remove { name: String },
+    /// Show the details of a dictionary
+    Info { name: Option<String> },
+    /// Check that a dictionary is well formed
+    Verify { name: Option<String> },
+}
+
+impl DictCommand {
+    /// Parses the arguments following `dict`
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut positional: Vec<&String> = Vec::new();
+        let mut name: Option<String> = None;
+        let mut language: Option<String> = None;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--name" => match args.next() {
+                    Some(value) => name = Some(value.clone()),
+                    None => return Err(anyhow!("'--name' requires a dictionary name")),
+                },
+                "--language" => match args.next() {
+                    Some(value) => language = Some(value.clone()),
+                    None => return Err(anyhow!("'--language' requires a language")),
+                },
+                flag if flag.starts_with("--") => {
+                    return Err(anyhow!("unknown option '{flag}'"));
+                }
+                _ => positional.push(arg),
+            }
+        }
+
+        let (subcommand, rest) = match positional.split_first() {
+            Some((subcommand, rest)) => (subcommand.as_str(), rest),
+            None => {
+                return Err(anyhow!(
+                    "missing dict subcommand (list, install, remove, info or verify)"
+                ))
+            }
+        };
+        if (name.is_some() || language.is_some()) && subcommand != "install" {
+            return Err(anyhow!(
+                "'--name' and '--language' can only be used with install"
+            ));
+        }
+
+        let command = match (subcommand, rest) {
+            ("list", []) => DictCommand::List,
+            ("install", [source]) => DictCommand::Install {
+                source: PathBuf::from(source),
+                name,
+                language,
+            },
+            ("remove", [name]) => DictCommand::Remove {
+                name: name.to_string(),
+            },
+            ("info", []) => DictCommand::Info { name: None },
+            ("info", [name]) => DictCommand::Info {
+                name: Some(name.to_string()),
+            },
+            ("verify", []) => DictCommand::Verify { name: None },
+            ("verify", [name]) => DictCommand::Verify {
+                name: Some(name.to_string()),
+            },
+            ("list" | "install" | "remove" | "info" | "verify", _) => {
+                return Err(anyhow!("wrong number of arguments for 'dict {subcommand}'"));
+            }
+            _ => return Err(anyhow!("unknown dict subcommand '{subcommand}'")),
+        };
+        Ok(command)
+    }
+
+    pub fn run(&self, locations: &Locations) -> Result<()> {
+        match self {
+            DictCommand::List => list(locations),
+            DictCommand::Install {
+                source,
+                name,
+                language,
+            } => install(locations, source, name.as_deref(), language.as_deref()),
+            DictCommand::Remove { name } => remove(locations, name),
+            DictCommand::Info { name } => info(&DictionaryFiles::find(locations, name.as_deref())?),
+            DictCommand::Verify { name } => {
+                verify_files(&DictionaryFiles::find(locations, name.as_deref())?)
+            }
+        }
+    }
+}
+
+/// The files of a single dictionary
+struct DictionaryFiles {
+    name: String,
+    dict: PathBuf,
+    suggestion_dict: Option<PathBuf>,
+    language: Option<String>,
+}
+
+impl DictionaryFiles {
+    /// Reads the dictionary installed in `dir`
+    fn read(name: &str, dir: &Path) -> Self {
+        let suggestion_dict = dir.join(SUGGESTION_DICT_FILE_NAME);
+        DictionaryFiles {
+            name: name.to_string(),
+            dict: dir.join(DICT_FILE_NAME),
+            suggestion_dict: suggestion_dict.is_file().then_some(suggestion_dict),
+            language: read_language(dir),
+        }
+    }
+
+    /// Finds the dictionary that would be used for checking - the one called `name`, or the one
+    /// selected with `--dict` or `--lang`. It is found even without a suggestion list, which
+    /// [`verify_files`] reports.
+    fn find(locations: &Locations, name: Option<&str>) -> Result<Self> {
+        let mut locations = locations.clone();
+        if let Some(name) = name {
+            locations.dictionary = Some(name.to_string());
+            locations.dict = None;
+        }
+
+        let paths = locations.find_dictionary()?;
+        let dict = paths.dict.ok_or_else(|| {
+            anyhow!(
+                "only the dictionary embedded in the program was found, which can't be inspected"
+            )
+        })?;
+
+        Ok(DictionaryFiles {
+            name: locations.dictionary_name().to_string(),
+            language: dict.parent().and_then(read_language),
+            dict,
+            suggestion_dict: paths.suggestion_dict,
+        })
+    }
+}
+
+impl DictionaryFiles {
+    /// Whether the suggestion list embedded in the program is used in place of a missing one. It
+    /// is English, so only the default dictionary can use it.
+    fn uses_embedded_suggestions(&self) -> bool {
+        self.suggestion_dict.is_none()
+            && self.name == DEFAULT_DICTIONARY
+            && embedded::is_available()
+    }
+}
+
+fn read_language(dir: &Path) -> Option<String> {
+    fs::read_to_string(dir.join(LANGUAGE_FILE_NAME))
+        .ok()
+        .map(|language| language.trim().to_string())
+        .filter(|language| !language.is_empty())
+}
+
+fn count_words(path: &Path) -> Result<usize> {
+    let contents = fs::read(path).with_context(|| format!("couldn't read {}", path.display()))?;
+    Ok(String::from_utf8_lossy(&contents)
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .count())
+}
+
+fn list(locations: &Locations) -> Result<()> {
+    let mut seen: Vec<String> = Vec::new();
+
+    for (data_dir, _) in locations.data_dirs() {
+        for name in paths::installed_dictionaries(&data_dir) {
+            let files = DictionaryFiles::read(&name, &dictionary_dir(&data_dir, &name));
+
+            // Dictionaries are searched for in order, so only the first one with a name is used
+            let marker = if seen.contains(&name) { ' ' } else { '*' };
+            seen.push(name.clone());
+
+            println!(
+                "{marker} {:<16} {:<10} {:>8} words  {}",
+                name,
+                files.language.as_deref().unwrap_or("-"),
+                count_words(&files.dict)?,
+                files.dict.display()
+            );
+        }
+    }
+
+    if !seen.iter().any(|name| name == DEFAULT_DICTIONARY) && embedded::is_available() {
+        println!(
+            "* {:<16} {:<10} {:>8}        (embedded)",
+            DEFAULT_DICTIONARY, "en", ""
+        );
+    }
+    Ok(())
+}
+
+fn info(files: &DictionaryFiles) -> Result<()> {
+    println!("name:               {}", files.name);
+    println!(
+        "language:           {}",
+        files.language.as_deref().unwrap_or("unknown")
+    );
+    println!("word list:          {}", files.dict.display());
+    println!("words:              {}", count_words(&files.dict)?);
+    match &files.suggestion_dict {
+        Some(path) => {
+            println!("suggestion list:    {}", path.display());
+            println!("suggestion words:   {}", count_words(path)?);
+        }
+        None if files.uses_embedded_suggestions() => println!("suggestion list:    (embedded)"),
+        None => println!("suggestion list:    missing, needed for checking"),
+    }
+    Ok(())
+}
+
+fn verify_files(files: &DictionaryFiles) -> Result<()> {
+    let mut problem_count: usize = 0;
+
+    let mut report = |path: &Path, problems: Vec<String>| {
+        for problem in problems.iter().take(MAX_REPORTED_PROBLEMS) {
+            println!("{}:{problem}", path.display());
+        }
+        if problems.len() > MAX_REPORTED_PROBLEMS {
+            println!(
+                "{}: ... and {} more",
+                path.display(),
+                problems.len() - MAX_REPORTED_PROBLEMS
+            );
+        }
+        problem_count += problems.len();
+    };
+
+    report(&files.dict, verify_word_list(&read_lossy(&files.dict)?));
+    match &files.suggestion_dict {
+        Some(path) => report(path, verify_suggestion_list(&read_lossy(path)?)),
+        None if files.uses_embedded_suggestions() => {}
+        None => report(
+            &files.dict.with_file_name(SUGGESTION_DICT_FILE_NAME),
+            vec![format!(
+                " missing, it is needed to check with the '{}' dictionary",
+                files.name
+            )],
+        ),
+    }
+
+    if problem_count > 0 {
+        return Err(anyhow!(
+            "found {problem_count} problems in the '{}' dictionary",
+            files.name
+        ));
+    }
+    println!("the '{}' dictionary is valid", files.name);
+    Ok(())
+}
+
+fn read_lossy(path: &Path) -> Result<String> {
+    let contents = fs::read(path).with_context(|| format!("couldn't read {}", path.display()))?;
+    Ok(String::from_utf8_lossy(&contents).into_owned())
+}
+
+/// Checks that the word list is in its canonical form: one word per line, sorted by the bytes of
+/// the lowercased words and without duplicates. Loading fixes the order and duplicates, but a
+/// canonical list doesn't have to be sorted on every compilation. Returns the problems found, each
+/// prefixed with the line number.
+pub fn verify_word_list(source: &str) -> Vec<String> {
+    let mut problems: Vec<String> = Vec::new();
+    let mut previous: Option<(String, &str)> = None; // The lowercased and the original word
+
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let word = line.trim();
+
+        if word.is_empty() {
+            problems.push(format!("{line_number}: empty line"));
+            continue;
+        }
+        if word.contains(char::is_whitespace) {
+            problems.push(format!("{line_number}: '{word}' contains whitespace"));
+        }
+
+        let key = (word.to_lowercase(), word);
+        match &previous {
+            Some(previous) if *previous == key => {
+                problems.push(format!("{line_number}: '{word}' is duplicated"));
+            }
+            Some(previous) if *previous > key => {
+                problems.push(format!(
+                    "{line_number}: '{word}' should come before '{}' (the list isn't sorted)",
+                    previous.1
+                ));
+            }
+            _ => {}
+        }
+        previous = Some(key);
+    }
+
+    problems
+}
+
+/// Checks that every line of the suggestion list is a word followed by its popularity. Returns
+/// the problems found, each prefixed with the line number.
+pub fn verify_suggestion_list(source: &str) -> Vec<String> {
+    let mut problems: Vec<String> = Vec::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let mut columns = line.split_ascii_whitespace();
+
+        match (columns.next(), columns.next(), columns.next()) {
+            (None, _, _) => problems.push(format!("{line_number}: empty line")),
+            (Some(word), None, _) => {
+                problems.push(format!("{line_number}: '{word}' is missing its popularity"))
+            }
+            (Some(_), Some(popularity), None) if popularity.parse::<i64>().is_err() => problems
+                .push(format!(
+                    "{line_number}: popularity '{popularity}' isn't a whole number"
+                )),
+            (Some(_), Some(_), Some(_)) => problems.push(format!(
+                "{line_number}: expected only a word and its popularity"
+            )),
+            _ => {}
+        }
+    }
+
+    problems
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || name.starts_with('.')
+        || name.contains(|c: char| c == '/' || c == '\\' || c.is_whitespace())
+    {
+        return Err(anyhow!("'{name}' isn't a valid dictionary name"));
+    }
+    Ok(())
+}
+
+fn is_archive(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    name.ends_with(".tar") || name.ends_with(".tar.gz") || name.ends_with(".tgz")
+}
+
+/// The name a dictionary installed from `source` gets if none is given
+fn default_name(source: &Path) -> String {
+    let file_name = source
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    for extension in [".tar.gz", ".tgz", ".tar", ".txt"] {
+        if let Some(stem) = file_name.strip_suffix(extension) {
+            return stem.to_string();
+        }
+    }
+    file_name
+}
+
+fn install(
+    locations: &Locations,
+    source: &Path,
+    name: Option<&str>,
+    language: Option<&str>,
+) -> Result<()> {
+    let name = name
+        .map(str::to_string)
+        .unwrap_or_else(|| default_name(source));
+    validate_name(&name)?;
+
+    let user_dir = locations.user_dir()?;
+    let destination = dictionary_dir(&user_dir, &name);
+    if destination.join(DICT_FILE_NAME).exists() {
+        return Err(anyhow!(
+            "the '{name}' dictionary is already installed in {}, remove it first with `spelchek dict remove {name}`",
+            destination.display()
+        ));
+    }
+
+    // Archives are unpacked into a temporary directory, and then installed like a directory
+    let unpacked_dir = user_dir.join(format!(".install-{}", std::process::id()));
+    let result = (|| {
+        let source_dir = if is_archive(source) {
+            unpack_archive(source, &unpacked_dir)?
+        } else {
+            source.to_path_buf()
+        };
+
+        if source_dir.is_dir() {
+            install_files(
+                &source_dir.join(DICT_FILE_NAME),
+                Some(&source_dir),
+                &destination,
+            )
+        } else {
+            install_files(source, None, &destination)
+        }
+    })();
+    let _ = fs::remove_dir_all(&unpacked_dir);
+    result?;
+
+    if let Some(language) = language {
+        fs::write(
+            destination.join(LANGUAGE_FILE_NAME),
+            format!("{language}\n"),
+        )?;
+    }
+
+    println!(
+        "installed the '{name}' dictionary ({} words) into {}",
+        count_words(&destination.join(DICT_FILE_NAME))?,
+        destination.display()
+    );
+    // The embedded suggestion list is English, so it isn't used in place of a missing one
+    let uses_embedded = name == DEFAULT_DICTIONARY && embedded::is_available();
+    if !uses_embedded && !destination.join(SUGGESTION_DICT_FILE_NAME).is_file() {
+        eprintln!(
+            "warning: the '{name}' dictionary has no {SUGGESTION_DICT_FILE_NAME}, add one with the popularity of its words to {} before checking with it",
+            destination.display()
+        );
+    }
+    Ok(())
+}
+
+/// Verifies and copies the word list at `dict` (and the other dictionary files from `source_dir`,
+/// if they are there) into `destination`.
+fn install_files(dict: &Path, source_dir: Option<&Path>, destination: &Path) -> Result<()> {
+    let suggestion_dict = source_dir
+        .map(|dir| dir.join(SUGGESTION_DICT_FILE_NAME))
+        .filter(|path| path.is_file());
+
+    let dict_source = read_lossy(dict)?;
+    let suggestion_source = match &suggestion_dict {
+        Some(path) => read_lossy(path)?,
+        None => String::new(),
+    };
+    Dictionary::from_sources(&dict_source, &suggestion_source)
+        .with_context(|| format!("{} isn't a valid dictionary", dict.display()))?;
+
+    // Ordering, casing and duplicates are fixed when the dictionary is loaded, so they are only
+    // worth a warning
+    let problems = verify_word_list(&dict_source);
+    for problem in problems.iter().take(MAX_REPORTED_PROBLEMS) {
+        eprintln!("warning: {}:{problem}", dict.display());
+    }
+
+    fs::create_dir_all(destination)?;
+    fs::copy(dict, destination.join(DICT_FILE_NAME))?;
+    if let Some(path) = suggestion_dict {
+        fs::copy(path, destination.join(SUGGESTION_DICT_FILE_NAME))?;
+    }
+    if let Some(language) = source_dir.and_then(read_language) {
+        fs::write(
+            destination.join(LANGUAGE_FILE_NAME),
+            format!("{language}\n"),
+        )?;
+    }
+
+    // Style guide rules and phrases shipped with the dictionary
+    if let Some(source_dir) = source_dir {
+        let style_file =
+            |file_name: &str| Some(source_dir.join(file_name)).filter(|path| path.is_file());
+        let (forbidden, replacements) = (
+            style_file(FORBIDDEN_FILE_NAME),
+            style_file(REPLACEMENTS_FILE_NAME),
+        );
+        StyleRules::load(forbidden.as_deref(), replacements.as_deref())?;
+
+        for (path, file_name) in [
+            (forbidden, FORBIDDEN_FILE_NAME),
+            (replacements, REPLACEMENTS_FILE_NAME),
+            (style_file(PHRASES_FILE_NAME), PHRASES_FILE_NAME),
+        ] {
+            if let Some(path) = path {
+                fs::copy(path, destination.join(file_name))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Unpacks a `.tar` or `.tar.gz` archive into `dir`, and returns the directory in it that holds
+/// the word list - either `dir` itself or its only subdirectory.
+fn unpack_archive(archive: &Path, dir: &Path) -> Result<PathBuf> {
+    let file =
+        fs::File::open(archive).with_context(|| format!("couldn't open {}", archive.display()))?;
+
+    let name = archive.to_string_lossy();
+    if name.ends_with(".tar") {
+        tar::Archive::new(file).unpack(dir)?;
+    } else {
+        tar::Archive::new(flate2::read::GzDecoder::new(file)).unpack(dir)?;
+    }
+
+    if dir.join(DICT_FILE_NAME).is_file() {
+        return Ok(dir.to_path_buf());
+    }
+
+    let subdirs: Vec<PathBuf> = fs::read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    match subdirs.as_slice() {
+        [subdir] if subdir.join(DICT_FILE_NAME).is_file() => Ok(subdir.clone()),
+        _ => Err(anyhow!(
+            "the archive doesn't contain a {DICT_FILE_NAME} at its top level"
+        )),
+    }
+}
+
+fn remove(locations: &Locations, name: &str) -> Result<()> {
+    validate_name(name)?;
+
+    let dir = dictionary_dir(&locations.user_dir()?, name);
+    if !dir.join(DICT_FILE_NAME).is_file() {
+        return Err(anyhow!(
+            "the '{name}' dictionary isn't installed in {}",
+            dir.display()
+        ));
+    }
+
+    if name == DEFAULT_DICTIONARY {
+        // The default dictionary shares its directory with other files, so only its own are
+        // removed
+        for file_name in [
+            DICT_FILE_NAME,
+            SUGGESTION_DICT_FILE_NAME,
+            LANGUAGE_FILE_NAME,
+            FORBIDDEN_FILE_NAME,
+            REPLACEMENTS_FILE_NAME,
+            PHRASES_FILE_NAME,
+            paths::COMPILED_DICT_FILE_NAME,
+        ] {
+            let path = dir.join(file_name);
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+    } else {
+        fs::remove_dir_all(&dir)?;
+    }
+
+    println!("removed the '{name}' dictionary from {}", dir.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_dir;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parsing() {
+        assert_eq!(
+            DictCommand::parse(&args(&["list"])).unwrap(),
+            DictCommand::List
+        );
+        assert_eq!(
+            DictCommand::parse(&args(&["install", "pl.tar.gz", "--language", "pl"])).unwrap(),
+            DictCommand::Install {
+                source: PathBuf::from("pl.tar.gz"),
+                name: None,
+                language: Some("pl".to_string())
+            }
+        );
+        assert_eq!(
+            DictCommand::parse(&args(&["verify"])).unwrap(),
+            DictCommand::Verify { name: None }
+        );
+        assert!(DictCommand::parse(&args(&[])).is_err());
+        assert!(DictCommand::parse(&args(&["remove"])).is_err());
+        assert!(DictCommand::parse(&args(&["list", "extra"])).is_err());
+        assert!(DictCommand::parse(&args(&["info", "--name", "x"])).is_err());
+        assert!(DictCommand::parse(&args(&["frobnicate"])).is_err());
+    }
+
+    #[test]
+    fn test_verifying_word_list() {
+        assert!(verify_word_list("a\napple\napples\nBanana\nbanana\nNASA\n").is_empty());
+        assert_eq!(
+            verify_word_list("apple\napple\nbanana\napples\n\nBlue\nred fox\n"),
+            vec![
+                "2: 'apple' is duplicated",
+                "4: 'apples' should come before 'banana' (the list isn't sorted)",
+                "5: empty line",
+                "7: 'red fox' contains whitespace",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verifying_suggestion_list() {
+        assert!(verify_suggestion_list("the\t100\nof 50\n").is_empty());
+        assert_eq!(
+            verify_suggestion_list("the\t100\nof\nand\tmany\nto 1 2\n"),
+            vec![
+                "2: 'of' is missing its popularity",
+                "3: popularity 'many' isn't a whole number",
+                "4: expected only a word and its popularity",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_name() {
+        assert_eq!(default_name(Path::new("/tmp/pl.tar.gz")), "pl");
+        assert_eq!(default_name(Path::new("medical.txt")), "medical");
+        assert_eq!(default_name(Path::new("dicts/en_GB")), "en_GB");
+        assert!(validate_name("../etc").is_err());
+        assert!(validate_name(".hidden").is_err());
+        assert!(validate_name("en_GB").is_ok());
+    }
+
+    #[test]
+    fn test_installing_and_removing() {
+        let dir = test_dir("dict-install");
+        let locations = Locations {
+            data_dir: Some(dir.join("data")),
+            ..Default::default()
+        };
+
+        let word_list = dir.join("medical.txt");
+        fs::write(&word_list, "aorta\nfemur\n").unwrap();
+        install(&locations, &word_list, None, Some("en")).unwrap();
+
+        let installed = dir.join("data/dictionaries/medical");
+        assert_eq!(
+            fs::read_to_string(installed.join(DICT_FILE_NAME)).unwrap(),
+            "aorta\nfemur\n"
+        );
+        assert_eq!(read_language(&installed), Some("en".to_string()));
+        assert_eq!(
+            paths::installed_dictionaries(&dir.join("data")),
+            vec!["medical".to_string()]
+        );
+
+        // It can be inspected, but it can't be used for checking without a suggestion list
+        let files = DictionaryFiles::find(&locations, Some("medical")).unwrap();
+        assert_eq!(files.suggestion_dict, None);
+        assert!(!files.uses_embedded_suggestions());
+        assert!(verify_files(&files).is_err());
+        let mut medical = locations.clone();
+        medical.dictionary = Some("medical".to_string());
+        assert!(medical.resolve_dictionary().is_err());
+
+        // Installing over an existing dictionary fails
+        assert!(install(&locations, &word_list, None, None).is_err());
+
+        remove(&locations, "medical").unwrap();
+        assert!(!installed.exists());
+        assert!(remove(&locations, "medical").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_installing_malformed_list() {
+        let dir = test_dir("dict-malformed");
+        let locations = Locations {
+            data_dir: Some(dir.join("data")),
+            ..Default::default()
+        };
+
+        let word_list = dir.join("words.txt");
+        fs::write(&word_list, "aorta\nfemur bone\n").unwrap();
+        assert!(install(&locations, &word_list, None, None).is_err());
+        assert!(!dir.join("data/dictionaries/words").exists());
+
+        // Unsorted lists are fixed when loaded
+        fs::write(&word_list, "femur\naorta\n").unwrap();
+        assert!(install(&locations, &word_list, None, None).is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_installing_archive() {
+        let dir = test_dir("dict-archive");
+        let locations = Locations {
+            data_dir: Some(dir.join("data")),
+            ..Default::default()
+        };
+
+        // An archive with the dictionary files in a single top level directory
+        let archive_path = dir.join("pl.tar.gz");
+        let encoder = flate2::write::GzEncoder::new(
+            fs::File::create(&archive_path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut builder = tar::Builder::new(encoder);
+        for (path, contents) in [
+            ("pl/dict.txt", "kot\npies\n"),
+            ("pl/suggestion_dict.txt", "kot\t10\npies\t5\n"),
+            ("pl/language", "pl\n"),
+            ("pl/replacements.txt", "kotek kot\n"),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, contents.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        install(&locations, &archive_path, None, None).unwrap();
+
+        let installed = dir.join("data/dictionaries/pl");
+        assert!(installed.join(SUGGESTION_DICT_FILE_NAME).is_file());
+        assert!(installed.join(REPLACEMENTS_FILE_NAME).is_file());
+        assert_eq!(read_language(&installed), Some("pl".to_string()));
+        assert!(!dir
+            .join(format!("data/.install-{}", std::process::id()))
+            .exists());
+
+        let files = DictionaryFiles::find(&locations, Some("pl")).unwrap();
+        assert_eq!(files.dict, installed.join(DICT_FILE_NAME));
+        assert!(verify_files(&files).is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
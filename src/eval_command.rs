@@ -0,0 +1,336 @@
+//! The `eval` subcommand, which measures the quality of the suggestions on a corpus of
+//! misspellings.
+//!
+//! ```text
+//! spelchek eval <corpus> [--show-misses] [--no-corrections]
+//! ```
+//!
+//! Two corpus formats are understood, and told apart by their contents:
+//! - the Birkbeck format, where a line starting with `$` holds the correct word, followed by its
+//!   misspellings one per line:
+//!   ```text
+//!   $neighbor
+//!   nieghbor
+//!   neigbor
+//!   ```
+//! - the Aspell test set format, holding a misspelling and its correction on every line:
+//!   ```text
+//!   nieghbor  neighbor
+//!   ```
+//!
+//! In both, underscores stand for spaces in multi-word corrections.
+//!
+//! The user's word list and correction history are left out, so that the results are the same for
+//! everyone with the same dictionary. The error model is still trained on the corrections corpus
+//! (the bundled `corrections.txt`, or the one passed with `--corrections`), so a corpus sharing
+//! misspellings with it measures the suggestions too favourably. `--no-corrections` leaves the model
+//! untrained, ranking the suggestions by their distance and popularity only.
+
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context};
+
+use crate::paths::Locations;
+use crate::prelude::*;
+use crate::spellchecker::channel::ErrorModel;
+use crate::spellchecker::dictionary::WordStatus;
+use crate::spellchecker::Spellchecker;
+
+/// The positions the correction is looked for among the suggestions
+const TOP_N: [usize; 3] = [1, 3, 10];
+
+#[derive(Debug, PartialEq)]
+pub struct EvalCommand {
+    corpus: PathBuf,
+    show_misses: bool, // Whether to list the misspellings whose correction wasn't suggested
+    no_corrections: bool, // Whether to leave the error model untrained
+}
+
+impl EvalCommand {
+    /// Parses the arguments following `eval`
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut corpus: Option<PathBuf> = None;
+        let mut show_misses = false;
+        let mut no_corrections = false;
+
+        for arg in args {
+            match arg.as_str() {
+                "--show-misses" => show_misses = true,
+                "--no-corrections" => no_corrections = true,
+                flag if flag.starts_with("--") => {
+                    return Err(anyhow!("unknown option '{flag}'"));
+                }
+                _ if corpus.is_none() => corpus = Some(PathBuf::from(arg)),
+                _ => return Err(anyhow!("unexpected argument '{arg}'")),
+            }
+        }
+
+        Ok(EvalCommand {
+            corpus: corpus.ok_or_else(|| anyhow!("'eval' requires the path of a corpus"))?,
+            show_misses,
+            no_corrections,
+        })
+    }
+
+    pub fn run(&self, locations: &Locations) -> Result<()> {
+        let source = fs::read(&self.corpus)
+            .with_context(|| format!("couldn't read {}", self.corpus.display()))?;
+        let cases = parse_corpus(&String::from_utf8_lossy(&source));
+        if cases.is_empty() {
+            return Err(anyhow!(
+                "{} doesn't hold any misspellings",
+                self.corpus.display()
+            ));
+        }
+
+        let mut spellchecker = Spellchecker::load_without_user_files(locations)?;
+        if self.no_corrections {
+            spellchecker.set_error_model(ErrorModel::default());
+        }
+
+        let report = evaluate(&spellchecker, &cases);
+        println!("corpus: {}", self.corpus.display());
+        print!("{report}");
+
+        if self.show_misses {
+            println!();
+            for (case, suggestions) in &report.misses {
+                println!(
+                    "{} -> {} (suggested: {})",
+                    case.misspelling,
+                    case.correction,
+                    suggestions.join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A misspelling together with its intended word
+#[derive(Debug, PartialEq, Clone)]
+pub struct Case {
+    pub misspelling: String,
+    pub correction: String,
+}
+
+/// Parses a corpus in the Birkbeck or the Aspell format.
+pub fn parse_corpus(source: &str) -> Vec<Case> {
+    let lines = source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    let word = |word: &str| word.replace('_', " ");
+
+    let mut cases = Vec::new();
+    if source.lines().any(|line| line.starts_with('$')) {
+        let mut correction: Option<String> = None;
+        for line in lines {
+            match line.strip_prefix('$') {
+                Some(correct) => correction = Some(word(correct)),
+                None => {
+                    if let Some(correction) = &correction {
+                        cases.push(Case {
+                            misspelling: word(line),
+                            correction: correction.clone(),
+                        });
+                    }
+                }
+            }
+        }
+    } else {
+        for line in lines {
+            let mut words = line.split_whitespace();
+            if let (Some(misspelling), Some(correction)) = (words.next(), words.next()) {
+                cases.push(Case {
+                    misspelling: word(misspelling),
+                    correction: word(correction),
+                });
+            }
+        }
+    }
+    cases
+}
+
+/// The results of running a corpus through the suggestions.
+#[derive(Debug)]
+pub struct Report {
+    pub cases: usize,
+    pub unknown_corrections: usize, // The corrections which aren't in any dictionary
+    pub found_in_top: [usize; TOP_N.len()], // The corrections found among the top N suggestions
+    pub latencies: Vec<Duration>,   // How long suggesting took for each case, sorted
+    pub misses: Vec<(Case, Vec<String>)>, // The cases whose correction wasn't suggested at all
+}
+
+impl Report {
+    /// The latency at the given percentile
+    fn percentile(&self, percentile: usize) -> Duration {
+        let index = (self.latencies.len() * percentile / 100).min(self.latencies.len() - 1);
+        self.latencies[index]
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} misspellings, {} of the corrections aren't in the dictionary",
+            self.cases, self.unknown_corrections
+        )?;
+        for (n, found) in TOP_N.iter().zip(self.found_in_top) {
+            writeln!(
+                f,
+                "top-{n:<3} {found:>6}/{}  {:5.1}%",
+                self.cases,
+                found as f64 * 100.0 / self.cases as f64
+            )?;
+        }
+
+        let total: Duration = self.latencies.iter().sum();
+        writeln!(
+            f,
+            "latency: mean {:.2?}, median {:.2?}, p95 {:.2?}, max {:.2?}",
+            total / self.cases as u32,
+            self.percentile(50),
+            self.percentile(95),
+            self.latencies.last().copied().unwrap_or_default()
+        )
+    }
+}
+
+/// Suggests corrections for every misspelling of the corpus, and counts how often the intended
+/// word is among the top suggestions.
+pub fn evaluate(spellchecker: &Spellchecker, cases: &[Case]) -> Report {
+    let mut report = Report {
+        cases: cases.len(),
+        unknown_corrections: 0,
+        found_in_top: [0; TOP_N.len()],
+        latencies: Vec::with_capacity(cases.len()),
+        misses: Vec::new(),
+    };
+
+    for case in cases {
+        if spellchecker.dictionaries().lookup(&case.correction).0 != WordStatus::Correct {
+            report.unknown_corrections += 1;
+        }
+
+        let start = Instant::now();
+        let suggestions = spellchecker.suggestions_for(&case.misspelling);
+        report.latencies.push(start.elapsed());
+
+        let position = suggestions
+            .iter()
+            .position(|suggestion| suggestion.to_lowercase() == case.correction.to_lowercase());
+        for (n, found) in TOP_N.iter().zip(report.found_in_top.iter_mut()) {
+            if position.is_some_and(|position| position < *n) {
+                *found += 1;
+            }
+        }
+        if position.is_none() {
+            report.misses.push((case.clone(), suggestions));
+        }
+    }
+
+    report.latencies.sort();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spellchecker::dictionary::Dictionary;
+    use crate::test_support::bundled_spellchecker;
+    use std::path::Path;
+
+    fn case(misspelling: &str, correction: &str) -> Case {
+        Case {
+            misspelling: misspelling.to_string(),
+            correction: correction.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parsing() {
+        let args = |args: &[&str]| -> Vec<String> { args.iter().map(|s| s.to_string()).collect() };
+        assert_eq!(
+            EvalCommand::parse(&args(&["corpus.dat", "--show-misses"])).unwrap(),
+            EvalCommand {
+                corpus: PathBuf::from("corpus.dat"),
+                show_misses: true,
+                no_corrections: false,
+            }
+        );
+        assert!(
+            EvalCommand::parse(&args(&["--no-corrections", "corpus.dat"]))
+                .unwrap()
+                .no_corrections
+        );
+        assert!(EvalCommand::parse(&args(&[])).is_err());
+        assert!(EvalCommand::parse(&args(&["a", "b"])).is_err());
+    }
+
+    #[test]
+    fn test_parsing_corpora() {
+        let birkbeck = "$neighbor\nnieghbor\nneigbor\n$a_lot\nalot\n";
+        assert_eq!(
+            parse_corpus(birkbeck),
+            vec![
+                case("nieghbor", "neighbor"),
+                case("neigbor", "neighbor"),
+                case("alot", "a lot")
+            ]
+        );
+
+        let aspell = "# misspelling correction\nnieghbor\tneighbor\nalot a_lot\nbroken\n";
+        assert_eq!(
+            parse_corpus(aspell),
+            vec![case("nieghbor", "neighbor"), case("alot", "a lot")]
+        );
+    }
+
+    #[test]
+    fn test_evaluating() {
+        let dict = Dictionary::from_sources(
+            "apple\napply\nbanana\ncherry\nneighbor\n",
+            "apple 100\napply 300\nbanana 50\ncherry 20\nneighbor 10\n",
+        )
+        .unwrap();
+        let spellchecker = Spellchecker::with_dictionary(dict);
+        let cases = [
+            case("aple", "apple"),
+            case("appla", "apple"),
+            case("bananna", "banana"),
+            case("nieghbor", "neighbor"),
+            case("xyzzy", "kiwi"),
+        ];
+
+        let report = evaluate(&spellchecker, &cases);
+        assert_eq!(report.cases, 5);
+        assert_eq!(report.unknown_corrections, 1);
+        assert_eq!(report.found_in_top, [3, 4, 4]);
+        assert_eq!(report.latencies.len(), 5);
+        assert_eq!(report.misses.len(), 1);
+        assert_eq!(report.misses[0].0, case("xyzzy", "kiwi"));
+    }
+
+    #[test]
+    #[ignore = "suggesting for the whole corpus is slow, run with `cargo test --release -- --ignored`"]
+    // The corpus of the repository, whose misspellings aren't in the corrections corpus the error
+    // model is trained on. The bounds are a little below the current results, so that a change
+    // making the ranking worse fails.
+    fn test_evaluating_corpus() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/misspellings.txt");
+        let cases = parse_corpus(&fs::read_to_string(path).unwrap());
+        let report = evaluate(&bundled_spellchecker(), &cases);
+
+        assert_eq!(report.cases, cases.len());
+        assert_eq!(report.unknown_corrections, 0);
+        let [top_1, top_3, top_10] = report.found_in_top.map(|found| found * 100 / report.cases);
+        assert!(top_1 >= 90, "top-1: {top_1}%");
+        assert!(top_3 >= 95, "top-3: {top_3}%");
+        assert!(top_10 >= 95, "top-10: {top_10}%");
+    }
+}
@@ -0,0 +1,259 @@
+//! Finding the files to check in the paths given on the command line, and checking many of them at
+//! the same time.
+//!
+//! Directories are walked recursively, skipping hidden files and the files ignored by
+//! `.gitignore`, `.ignore` or `.spelchekignore` files (which use the `.gitignore` syntax). Files
+//! passed explicitly are always checked.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use rayon::prelude::*;
+
+use crate::paths::project_words_path;
+use crate::prelude::*;
+use crate::spellchecker::tokenizer::Tokenizer;
+use crate::spellchecker::{Misspelling, Spellchecker};
+
+/// The name of the files listing the files which shouldn't be spellchecked
+pub const IGNORE_FILE_NAME: &str = ".spelchekignore";
+
+/// Which of the files found in the walked directories are checked
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FileFilter {
+    pub include: Vec<String>, // If not empty, only the files matching one of these globs are checked
+    pub exclude: Vec<String>, // The files matching one of these globs are never checked
+}
+
+/// Returns the files to check: the files among `paths`, and the files found in the directories
+/// among them, in order and without duplicates.
+pub fn find_files(paths: &[PathBuf], filter: &FileFilter) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+
+        // The globs are matched against the paths relative to the walked directory. Only the
+        // excluded globs are given to the walk, as its included globs would take precedence over
+        // the ignore files.
+        let mut excluded = OverrideBuilder::new(path);
+        for glob in &filter.exclude {
+            excluded.add(&format!("!{glob}"))?;
+        }
+        let mut included = OverrideBuilder::new(path);
+        for glob in &filter.include {
+            included.add(glob)?;
+        }
+        let included = included.build()?;
+
+        let walk = WalkBuilder::new(path)
+            .overrides(excluded.build()?)
+            .add_custom_ignore_filename(IGNORE_FILE_NAME)
+            .require_git(false)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .build();
+        for entry in walk {
+            let entry = entry?;
+            let is_file = entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file());
+            if is_file
+                && (included.is_empty() || included.matched(entry.path(), false).is_whitelist())
+            {
+                files.push(entry.into_path());
+            }
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    files.retain(|file| seen.insert(file.clone()));
+    Ok(files)
+}
+
+/// A file read and checked for misspellings
+#[derive(Debug)]
+pub struct CheckedFile {
+    pub path: PathBuf,
+    pub text: String,
+    pub tokenizer: Arc<dyn Tokenizer>,
+    pub misspellings: Vec<Misspelling>,
+}
+
+/// Reads and checks the files in parallel, passing each checked file to `then` (also called in
+/// parallel) and returning the results in the order of the files. Binary files are skipped.
+///
+/// The project word list of every file is loaded into the spellchecker before checking it, which
+/// is why it has to be mutable.
+pub fn check_files<T: Send>(
+    files: &[PathBuf],
+    spellchecker: &mut Spellchecker,
+    tokenizer_for: &(dyn Fn(&Path) -> Result<Arc<dyn Tokenizer>> + Sync),
+    then: &(dyn Fn(&Spellchecker, CheckedFile) -> T + Sync),
+) -> Result<Vec<T>> {
+    // The files sharing a project word list are checked together
+    let mut projects: BTreeMap<PathBuf, Vec<usize>> = BTreeMap::new();
+    for (i, file) in files.iter().enumerate() {
+        projects
+            .entry(project_words_path(file))
+            .or_default()
+            .push(i);
+    }
+
+    let mut results: Vec<(usize, T)> = Vec::with_capacity(files.len());
+    for indices in projects.values() {
+        spellchecker.load_project_words(&files[indices[0]])?;
+        let spellchecker = &*spellchecker;
+        let checked: Vec<Option<(usize, T)>> = indices
+            .par_iter()
+            .map(|&i| {
+                let path = &files[i];
+                let bytes =
+                    fs::read(path).with_context(|| format!("couldn't read {}", path.display()))?;
+                if bytes.contains(&0) {
+                    return Ok(None);
+                }
+
+                let text = String::from_utf8_lossy(&bytes).to_string();
+                let tokenizer = tokenizer_for(path)?;
+                let misspellings = spellchecker.find_misspellings(&text, tokenizer.as_ref());
+                let file = CheckedFile {
+                    path: path.clone(),
+                    text,
+                    tokenizer,
+                    misspellings,
+                };
+                Ok(Some((i, then(spellchecker, file))))
+            })
+            .collect::<Result<_>>()?;
+        results.extend(checked.into_iter().flatten());
+    }
+
+    results.sort_by_key(|(i, _)| *i);
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spellchecker::dictionary::Dictionary;
+    use crate::spellchecker::tokenizer::PlainText;
+    use crate::test_support::test_dir;
+
+    /// Creates a directory tree of files with the given contents in the system's temporary
+    /// directory
+    fn create_tree(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = test_dir(name);
+        for (path, contents) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        root
+    }
+
+    fn names(root: &Path, files: &[PathBuf]) -> Vec<String> {
+        files
+            .iter()
+            .map(|file| file.strip_prefix(root).unwrap().display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_finding_files() {
+        let root = create_tree(
+            "find",
+            &[
+                ("README.md", ""),
+                ("docs/guide.md", ""),
+                ("docs/notes.txt", ""),
+                ("target/out.txt", ""),
+                ("drafts/old.md", ""),
+                (".hidden.md", ""),
+                (".gitignore", "target/\n"),
+                (IGNORE_FILE_NAME, "drafts/\n"),
+            ],
+        );
+
+        let files = find_files(std::slice::from_ref(&root), &FileFilter::default()).unwrap();
+        assert_eq!(
+            names(&root, &files),
+            ["README.md", "docs/guide.md", "docs/notes.txt"]
+        );
+
+        let filter = FileFilter {
+            include: vec!["*.md".to_string()],
+            exclude: vec!["docs/guide.md".to_string()],
+        };
+        let files = find_files(std::slice::from_ref(&root), &filter).unwrap();
+        assert_eq!(names(&root, &files), ["README.md"]);
+
+        // The included globs don't bring back the ignored files
+        let filter = FileFilter {
+            include: vec!["*.md".to_string(), "*.txt".to_string()],
+            exclude: Vec::new(),
+        };
+        let files = find_files(std::slice::from_ref(&root), &filter).unwrap();
+        assert_eq!(
+            names(&root, &files),
+            ["README.md", "docs/guide.md", "docs/notes.txt"]
+        );
+
+        // Files passed explicitly are checked even if ignored, but only once
+        let paths = [
+            root.join("drafts/old.md"),
+            root.clone(),
+            root.join("README.md"),
+        ];
+        let files = find_files(&paths, &FileFilter::default()).unwrap();
+        assert_eq!(
+            names(&root, &files),
+            [
+                "drafts/old.md",
+                "README.md",
+                "docs/guide.md",
+                "docs/notes.txt"
+            ]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_checking_files() {
+        let root = create_tree(
+            "check",
+            &[
+                ("a.txt", "some txet"),
+                ("b.txt", "text"),
+                ("c.bin", "txet\0"),
+                ("d.txt", "sme txet"),
+            ],
+        );
+        let files = find_files(std::slice::from_ref(&root), &FileFilter::default()).unwrap();
+        let dict = Dictionary::from_sources("some\ntext\n", "some 10\ntext 10\n").unwrap();
+        let mut spellchecker = Spellchecker::with_dictionary(dict);
+
+        let counts = check_files(
+            &files,
+            &mut spellchecker,
+            &|_| Ok(Arc::new(PlainText)),
+            &|_, file| (file.path, file.misspellings.len()),
+        )
+        .unwrap();
+        assert_eq!(
+            counts,
+            [
+                (root.join("a.txt"), 1),
+                (root.join("b.txt"), 0),
+                (root.join("d.txt"), 2)
+            ]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,322 @@
+//! The `--fix` mode, correcting the misspellings whose correction is unambiguous without the TUI,
+//! and leaving the others for review.
+//!
+//! A misspelling is corrected with its top suggestion only when:
+//! - the suggestion is strictly closer to the misspelling than all of the other suggestions, by the
+//!   edit distance counting a swap of two letters as one edit,
+//! - and the suggestion is a common word, at least as popular as the `--min-popularity` given.
+//!
+//! With `--popularity-margin <n>`, a suggestion as close as some others is also applied when it is
+//! at least `n` times as popular as each of them.
+//!
+//! Words written in the wrong case are corrected when there is only one right casing, and terms
+//! replaced by the style guide with its preferred term. Forbidden words are always left for review.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::app_state::{correction_for, AppState};
+use crate::report::LineIndex;
+use crate::spellchecker::algorithm::transposition_distance;
+use crate::spellchecker::layers::DictionaryStack;
+use crate::spellchecker::{Misspelling, MisspellingKind};
+
+/// The popularity a suggestion needs by default to be applied. About a third of the words in the
+/// bundled suggestion list are this common.
+pub const DEFAULT_MIN_POPULARITY: i64 = 10_000_000;
+
+/// How sure the fix has to be of a suggestion to apply it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Confidence {
+    pub min_popularity: i64, // How popular the suggestion has to be
+    // How many times more popular than the other suggestions as close to the misspelling the top
+    // suggestion has to be to be applied anyway, if ties can be broken by popularity at all
+    pub popularity_margin: Option<f64>,
+}
+
+impl Default for Confidence {
+    fn default() -> Self {
+        Confidence {
+            min_popularity: DEFAULT_MIN_POPULARITY,
+            popularity_margin: None,
+        }
+    }
+}
+
+/// Why a misspelling was left for review
+#[derive(Debug, PartialEq, Clone)]
+pub enum Uncertainty {
+    NoSuggestions,
+    Forbidden,
+    Ambiguous(Vec<String>), // The suggestions which are equally close to the misspelling
+    Rare(String),           // The best suggestion isn't popular enough
+}
+
+impl fmt::Display for Uncertainty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Uncertainty::NoSuggestions => write!(f, "no suggestions"),
+            Uncertainty::Forbidden => write!(f, "forbidden by the style guide"),
+            Uncertainty::Ambiguous(suggestions) => {
+                write!(f, "ambiguous between {}", suggestions.join(", "))
+            }
+            Uncertainty::Rare(suggestion) => write!(f, "'{suggestion}' is an uncommon word"),
+        }
+    }
+}
+
+/// What was done with a misspelling
+#[derive(Debug, PartialEq, Clone)]
+pub enum Outcome {
+    Fixed(String), // The text it was replaced with
+    Left(Uncertainty),
+}
+
+/// A misspelling found by the fix, at its position in the fixed text
+#[derive(Debug, PartialEq, Clone)]
+pub struct FixedMisspelling {
+    pub word: String,
+    pub start: usize, // The byte offset of the misspelling, or of its correction
+    pub outcome: Outcome,
+}
+
+/// Decides which of the misspelling's suggestions can be applied, if any
+pub fn choose(
+    misspelling: &Misspelling,
+    dicts: &DictionaryStack,
+    confidence: Confidence,
+) -> Result<usize, Uncertainty> {
+    let suggestions = misspelling.get_suggestions();
+    match misspelling.get_kind() {
+        MisspellingKind::Forbidden => return Err(Uncertainty::Forbidden),
+        _ if suggestions.is_empty() => return Err(Uncertainty::NoSuggestions),
+        MisspellingKind::Replacement => return Ok(0),
+        MisspellingKind::Capitalization if suggestions.len() == 1 => return Ok(0),
+        MisspellingKind::Capitalization => return Err(Uncertainty::Ambiguous(suggestions.clone())),
+        MisspellingKind::Spelling => {}
+    }
+
+    let word = misspelling.get_word().to_lowercase();
+    let distances: Vec<usize> = suggestions
+        .iter()
+        .map(|suggestion| transposition_distance(&word, &suggestion.to_lowercase()))
+        .collect();
+    let closest = *distances.iter().min().expect("there are suggestions");
+    let tied: Vec<&String> = suggestions
+        .iter()
+        .zip(&distances)
+        .filter(|(_, distance)| **distance == closest)
+        .map(|(suggestion, _)| suggestion)
+        .collect();
+
+    let popularity = |word: &str| dicts.popularity(word).unwrap_or(0);
+    let best = popularity(&suggestions[0]);
+    let dominates = |margin: f64| {
+        tied[1..]
+            .iter()
+            .all(|other| popularity(other) as f64 * margin <= best as f64)
+    };
+    let is_tied = tied.len() > 1 && !confidence.popularity_margin.is_some_and(dominates);
+    if distances[0] != closest || is_tied {
+        return Err(Uncertainty::Ambiguous(tied.into_iter().cloned().collect()));
+    }
+
+    match best >= confidence.min_popularity {
+        true => Ok(0),
+        false => Err(Uncertainty::Rare(suggestions[0].clone())),
+    }
+}
+
+/// Fixes the misspellings of the file being checked in the app, using the same replacement as
+/// accepting a suggestion in the TUI. Returns what was done with each of the misspellings.
+pub fn fix_file(app: &mut AppState, confidence: Confidence) -> Vec<FixedMisspelling> {
+    let mut fixed = Vec::new();
+
+    // The misspellings after the current one are moved by the corrections, the ones before it are
+    // not
+    let mut i = 0;
+    while i < app.spellchecker.misspellings().len() {
+        app.selected_misspelling = Some(i);
+        app.suggest_selected();
+        let misspelling = &app.spellchecker.misspellings()[i];
+        let (word, start) = (misspelling.get_word().clone(), misspelling.get_start());
+
+        let dicts = app.spellchecker.checker_for(misspelling).dictionaries();
+        match choose(misspelling, dicts, confidence) {
+            Ok(suggestion) => {
+                let correction = correction_for(&word, &misspelling.get_suggestions()[suggestion]);
+                app.selected_suggestion = Some(suggestion);
+                app.apply_suggestion();
+                fixed.push(FixedMisspelling {
+                    word,
+                    start,
+                    outcome: Outcome::Fixed(correction),
+                });
+            }
+            Err(uncertainty) => {
+                fixed.push(FixedMisspelling {
+                    word,
+                    start,
+                    outcome: Outcome::Left(uncertainty),
+                });
+                i += 1;
+            }
+        }
+    }
+
+    app.selected_misspelling = None;
+    app.selected_suggestion = None;
+    fixed
+}
+
+/// Writes what was done with the misspellings of the file at `path`, whose fixed text is `text`
+pub fn write_report(
+    out: &mut impl Write,
+    path: &Path,
+    text: &str,
+    fixed: &[FixedMisspelling],
+) -> io::Result<()> {
+    let lines = LineIndex::new(text);
+    for misspelling in fixed {
+        let position = lines.position(misspelling.start);
+        let location = format!("{}:{}:{}", path.display(), position.line, position.column);
+        match &misspelling.outcome {
+            Outcome::Fixed(correction) => {
+                writeln!(out, "{location}: {} -> {correction}", misspelling.word)?
+            }
+            Outcome::Left(uncertainty) => writeln!(
+                out,
+                "{location}: {} (left for review: {uncertainty})",
+                misspelling.word
+            )?,
+        }
+    }
+    Ok(())
+}
+
+/// Writes how many misspellings were fixed and how many were left for review
+pub fn write_summary(out: &mut impl Write, fixed: usize, left: usize) -> io::Result<()> {
+    writeln!(out, "fixed {fixed} misspellings, left {left} for review")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spellchecker::dictionary::Dictionary;
+    use crate::spellchecker::history::CorrectionHistory;
+    use crate::spellchecker::Spellchecker;
+    use std::path::PathBuf;
+
+    fn app_state_for(text: &str) -> AppState {
+        let dict = Dictionary::from_sources(
+            "cat\ncar\nsome\ntea\ntext\nthe\nzebra\n",
+            "cat 20000000\ncar 20000000\nsome 50000000\ntea 1000000\ntext 30000000\n\
+             the 90000000\nzebra 1000\n",
+        )
+        .unwrap();
+        let mut spellchecker = Spellchecker::with_dictionary(dict);
+        spellchecker.set_history(CorrectionHistory::default());
+        let mut app =
+            AppState::with_spellchecker(PathBuf::from("/"), text.to_string(), spellchecker)
+                .unwrap();
+        app.check_spelling();
+        app
+    }
+
+    #[test]
+    fn test_fixing() {
+        let mut app = app_state_for("Teh cax ate soem zebrra text");
+        let fixed = fix_file(&mut app, Confidence::default());
+
+        assert_eq!(app.get_buffer(), "Teh cax ate some zebrra text");
+        let outcomes: Vec<(&str, &Outcome)> = fixed
+            .iter()
+            .map(|misspelling| (misspelling.word.as_str(), &misspelling.outcome))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [
+                (
+                    "Teh",
+                    &Outcome::Left(Uncertainty::Ambiguous(vec![
+                        "the".to_string(),
+                        "tea".to_string()
+                    ]))
+                ),
+                (
+                    "cax",
+                    &Outcome::Left(Uncertainty::Ambiguous(vec![
+                        "car".to_string(),
+                        "cat".to_string()
+                    ]))
+                ),
+                (
+                    "ate",
+                    &Outcome::Left(Uncertainty::Ambiguous(vec![
+                        "the".to_string(),
+                        "cat".to_string(),
+                        "tea".to_string()
+                    ]))
+                ),
+                ("soem", &Outcome::Fixed("some".to_string())),
+                (
+                    "zebrra",
+                    &Outcome::Left(Uncertainty::Rare("zebra".to_string()))
+                ),
+            ]
+        );
+        // The misspellings left for review are still there to be reviewed
+        assert_eq!(app.spellchecker.misspellings().len(), 4);
+    }
+
+    #[test]
+    // "Teh" is as close to "tea" as to "the", which is much more popular
+    fn test_breaking_ties_by_popularity() {
+        let confidence = Confidence {
+            popularity_margin: Some(50.0),
+            ..Confidence::default()
+        };
+        let mut app = app_state_for("Teh cax");
+        let fixed = fix_file(&mut app, confidence);
+        assert_eq!(app.get_buffer(), "The cax");
+        assert_eq!(fixed[0].outcome, Outcome::Fixed("The".to_string()));
+
+        // "car" and "cat" are as popular
+        assert!(matches!(
+            fixed[1].outcome,
+            Outcome::Left(Uncertainty::Ambiguous(_))
+        ));
+
+        // Not popular enough
+        let confidence = Confidence {
+            popularity_margin: Some(100.0),
+            ..Confidence::default()
+        };
+        let mut app = app_state_for("Teh");
+        fix_file(&mut app, confidence);
+        assert_eq!(app.get_buffer(), "Teh");
+    }
+
+    #[test]
+    fn test_report() {
+        let fixed = [
+            FixedMisspelling {
+                word: "teh".to_string(),
+                start: 7,
+                outcome: Outcome::Fixed("the".to_string()),
+            },
+            FixedMisspelling {
+                word: "zebrra".to_string(),
+                start: 11,
+                outcome: Outcome::Left(Uncertainty::Rare("zebra".to_string())),
+            },
+        ];
+        let mut out: Vec<u8> = Vec::new();
+        write_report(&mut out, Path::new("a.txt"), "text\n  the zebrra", &fixed).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "a.txt:2:3: teh -> the\na.txt:2:7: zebrra (left for review: 'zebra' is an uncommon word)\n"
+        );
+    }
+}
@@ -6,10 +6,35 @@ pub mod update; // updates every frame, handles input
 // the actual logic behind spellchecking
 pub mod spellchecker; // the main module controlling spellchecking
 
+pub mod dict_command; // the `dict` subcommand, managing installed dictionaries
+pub mod eval_command; // the `eval` subcommand, measuring the quality of the suggestions
+pub mod files; // finding and checking the files in the given directories
+pub mod fix; // correcting the unambiguous misspellings without the TUI
+pub mod lsp_command; // the `lsp` subcommand, a language server
+pub mod patch; // keeping the corrections as a diff instead of saving the files
+pub mod paths; // locating the dictionaries and other program files
+pub mod pipe_command; // the `-a` mode, speaking the ispell pipe protocol
 pub mod prelude; // global exports and other
+pub mod report; // printing the misspellings without the TUI
+pub mod settings; // reading the settings from the configuration files
+#[cfg(test)]
+mod test_support; // helpers shared by the tests
 use crate::prelude::*;
+use anyhow::Context;
 
 use crate::app_state::AppState;
+use crate::dict_command::DictCommand;
+use crate::eval_command::EvalCommand;
+use crate::files::FileFilter;
+use crate::fix::Confidence;
+use crate::lsp_command::LspCommand;
+use crate::patch::PatchTarget;
+use crate::paths::{find_upwards, Locations, DEFAULT_DICTIONARY};
+use crate::pipe_command::PipeCommand;
+use crate::report::{OutputFormat, Threshold};
+use crate::settings::{Settings, CONFIG_FILE_NAME, USER_CONFIG_FILE_NAME};
+use crate::spellchecker::tokenizer::{Tokenizer, TokenizerRegistry};
+use crate::spellchecker::{Spellchecker, SuggestionLimits};
 
 use crossterm::execute;
 use crossterm::terminal::{
@@ -18,77 +43,747 @@ use crossterm::terminal::{
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
 
-use std::io::{self, Write};
+use std::collections::BTreeMap;
+use std::env;
+use std::io::{self, IsTerminal, Read, Write};
 
-use std::fs::{self, canonicalize};
+use std::fs::{canonicalize, OpenOptions};
 use std::panic;
 use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// The exit code of `spelchek check` when it finds more misspellings than allowed
+pub const EXIT_MISSPELLINGS: u8 = 1;
+/// The exit code when the program can't do its job, like when the dictionary or the checked file
+/// can't be read
+pub const EXIT_ERROR: u8 = 2;
+
+/// The file path standing for stdin
+pub const STDIN_PATH: &str = "-";
+/// The name the text read from stdin is reported under
+const STDIN_NAME: &str = "<stdin>";
+/// The terminal the TUI is drawn on when stdout is taken by the corrected text
+const TTY_PATH: &str = "/dev/tty";
+
+/// Whether the TUI has taken over the terminal, which then has to be restored
+static TERMINAL_STARTED: AtomicBool = AtomicBool::new(false);
+/// Whether the TUI is drawn on [`TTY_PATH`] rather than on stdout
+static TERMINAL_ON_TTY: AtomicBool = AtomicBool::new(false);
+
+/// What the program was asked to do
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Interactively spellcheck the files (`spelchek <path>...`)
+    Interactive,
+    /// Spellcheck the files without the TUI, printing the misspellings (`spelchek check <path>...`)
+    Check,
+    /// Correct the unambiguous misspellings without the TUI (`spelchek --fix <path>...`)
+    Fix,
+    /// Manage the installed dictionaries
+    Dict(DictCommand),
+    /// Measure the quality of the suggestions on a corpus of misspellings
+    Eval(EvalCommand),
+    /// Answer the lines of stdin like `ispell -a`, or print the version like `ispell -v`
+    Pipe(PipeCommand),
+    /// Spellcheck the documents open in an editor, as a language server
+    Lsp(LspCommand),
+}
 
 pub struct Config {
-    spellchecked_file_path: PathBuf,
+    command: Command,
+    spellchecked_paths: Vec<PathBuf>, // Files and directories, empty for subcommands, `-` for stdin
+    filter: FileFilter,               // Which of the files in the directories are checked
+    format: Option<String>, // The name of the tokenizer to use, guessed from the file if `None`
+    po_check_msgid: bool,   // Whether the source strings of .po files should also be checked
+    po_source_lang: Option<String>, // The dictionary of the source strings
+    output_format: OutputFormat, // How `check` reports the misspellings
+    threshold: Threshold,   // When `check` fails
+    confidence: Confidence, // Which misspellings `--fix` corrects
+    patch: Option<PatchTarget>, // Where the diff goes, if the files aren't overwritten
+    limits: SuggestionLimits, // How many suggestions are offered, and how far they can be
+    formats: BTreeMap<String, String>, // The format names by file extension, from the settings
+    tokenizers: TokenizerRegistry,
+    locations: Locations, // Where the dictionaries are
 }
 
 impl Config {
     pub fn build(args: &[String]) -> Result<Self> {
-        let requested_file_path = match args.first() {
-            Some(arg) => arg,
-            None => {
-                return Err(anyhow::anyhow!("not enough arguments given"));
+        let mut requested_paths: Vec<&String> = Vec::new();
+        let mut filter = FileFilter::default();
+        let mut format: Option<String> = None;
+        let mut output_format: Option<OutputFormat> = None;
+        let mut threshold: Option<Threshold> = None;
+        let mut po_check_msgid = false;
+        let mut po_source_lang: Option<String> = None;
+        let mut check = false; // Whether the files should be checked without the TUI (`check`)
+        let mut fix = false; // Whether the misspellings should be corrected without the TUI
+        let mut min_popularity: Option<i64> = None;
+        let mut popularity_margin: Option<f64> = None;
+        let mut patch: Option<PatchTarget> = None;
+        let mut locations = Locations::default();
+        // The name and the arguments of the subcommand (`dict`, `eval`, `lsp` or `-a`), if there is
+        // one
+        let mut subcommand: Option<(&str, Vec<String>)> = None;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--format" => match args.next() {
+                    Some(name) => format = Some(name.clone()),
+                    None => return Err(anyhow::anyhow!("'--format' requires a format name")),
+                },
+                "--output" => match args.next() {
+                    Some(name) => output_format = Some(OutputFormat::from_name(name)?),
+                    None => {
+                        return Err(anyhow::anyhow!(
+                            "'--output' requires one of {}",
+                            OutputFormat::NAMES.join(", ")
+                        ))
+                    }
+                },
+                "--po-check-msgid" => po_check_msgid = true,
+                "--po-source-lang" => match args.next() {
+                    Some(name) => po_source_lang = Some(name.clone()),
+                    None => {
+                        return Err(anyhow::anyhow!(
+                            "'--po-source-lang' requires a dictionary name"
+                        ))
+                    }
+                },
+                "--max-misspellings" => match args.next().map(|n| n.parse::<usize>()) {
+                    Some(Ok(max)) => threshold.get_or_insert_with(Threshold::default).max = max,
+                    _ => {
+                        return Err(anyhow::anyhow!(
+                            "'--max-misspellings' requires a whole number"
+                        ))
+                    }
+                },
+                "--warn-only" => threshold.get_or_insert_with(Threshold::default).warn_only = true,
+                "--fix" => fix = true,
+                "--min-popularity" => match args.next().map(|n| n.parse::<i64>()) {
+                    Some(Ok(min)) => min_popularity = Some(min),
+                    _ => return Err(anyhow::anyhow!("'--min-popularity' requires a number")),
+                },
+                "--popularity-margin" => match args.next().map(|n| n.parse::<f64>()) {
+                    Some(Ok(margin)) if margin >= 1.0 => popularity_margin = Some(margin),
+                    _ => {
+                        return Err(anyhow::anyhow!(
+                            "'--popularity-margin' requires a number of at least 1"
+                        ))
+                    }
+                },
+                "--diff" | "--patch" if patch.is_some() => {
+                    return Err(anyhow::anyhow!(
+                        "'--diff' and '--patch' can't be used together"
+                    ))
+                }
+                "--diff" => patch = Some(PatchTarget::Stdout),
+                "--patch" => match args.next() {
+                    Some(path) => patch = Some(PatchTarget::File(PathBuf::from(path))),
+                    None => return Err(anyhow::anyhow!("'--patch' requires a path")),
+                },
+                "--dict" => match args.next() {
+                    Some(path) => locations.dict = Some(PathBuf::from(path)),
+                    None => return Err(anyhow::anyhow!("'--dict' requires a path")),
+                },
+                "--data-dir" => match args.next() {
+                    Some(path) => locations.data_dir = Some(PathBuf::from(path)),
+                    None => return Err(anyhow::anyhow!("'--data-dir' requires a path")),
+                },
+                "--forbidden" => match args.next() {
+                    Some(path) => locations.forbidden = Some(PathBuf::from(path)),
+                    None => return Err(anyhow::anyhow!("'--forbidden' requires a path")),
+                },
+                "--replacements" => match args.next() {
+                    Some(path) => locations.replacements = Some(PathBuf::from(path)),
+                    None => return Err(anyhow::anyhow!("'--replacements' requires a path")),
+                },
+                "--corrections" => match args.next() {
+                    Some(path) => locations.corrections = Some(PathBuf::from(path)),
+                    None => return Err(anyhow::anyhow!("'--corrections' requires a path")),
+                },
+                "--phrases" => match args.next() {
+                    Some(path) => locations.phrases = Some(PathBuf::from(path)),
+                    None => return Err(anyhow::anyhow!("'--phrases' requires a path")),
+                },
+                "--include" => match args.next() {
+                    Some(glob) => filter.include.push(glob.clone()),
+                    None => return Err(anyhow::anyhow!("'--include' requires a glob")),
+                },
+                "--exclude" => match args.next() {
+                    Some(glob) => filter.exclude.push(glob.clone()),
+                    None => return Err(anyhow::anyhow!("'--exclude' requires a glob")),
+                },
+                "--lang" => match args.next() {
+                    Some(name) => locations.dictionary = Some(name.clone()),
+                    None => return Err(anyhow::anyhow!("'--lang' requires a dictionary name")),
+                },
+                // Everything else after a subcommand is left for it to parse
+                _ if subcommand.is_some() => subcommand.as_mut().unwrap().1.push(arg.clone()),
+                flag if flag.starts_with("--") => {
+                    return Err(anyhow::anyhow!("unknown option '{flag}'"));
+                }
+                name @ ("dict" | "eval" | "lsp" | "-a" | "-v" | "-vv")
+                    if requested_paths.is_empty() && !check =>
+                {
+                    subcommand = Some((name, Vec::new()))
+                }
+                "check" if requested_paths.is_empty() && !check => check = true,
+                _ => requested_paths.push(arg),
             }
+        }
+
+        if let Some((name, args)) = subcommand {
+            let command = match name {
+                "dict" => Command::Dict(DictCommand::parse(&args)?),
+                "eval" => Command::Eval(EvalCommand::parse(&args)?),
+                "lsp" => Command::Lsp(LspCommand::parse(&args)?),
+                "-a" => Command::Pipe(PipeCommand::parse(&args)?),
+                _ => Command::Pipe(PipeCommand::Version),
+            };
+            let is_version = command == Command::Pipe(PipeCommand::Version);
+            let mut config = Self {
+                command,
+                spellchecked_paths: Vec::new(),
+                filter,
+                format,
+                po_check_msgid,
+                po_source_lang,
+                output_format: OutputFormat::default(),
+                threshold: Threshold::default(),
+                confidence: Confidence::default(),
+                patch: None,
+                limits: SuggestionLimits::default(),
+                formats: BTreeMap::new(),
+                tokenizers: TokenizerRegistry::default(),
+                locations,
+            };
+            // Printing the version can't fail, even with invalid configuration files
+            if !is_version {
+                config.apply_settings(None, None)?;
+            }
+            return Ok(config);
+        }
+
+        if output_format.is_some() && !check {
+            return Err(anyhow::anyhow!(
+                "the output format can only be chosen for `spelchek check`"
+            ));
+        }
+        if threshold.is_some() && !check {
+            return Err(anyhow::anyhow!(
+                "'--max-misspellings' and '--warn-only' can only be used with `spelchek check`"
+            ));
+        }
+
+        if fix && check {
+            return Err(anyhow::anyhow!(
+                "'--fix' can't be used with `spelchek check`"
+            ));
+        }
+        if !fix {
+            let flag = match (min_popularity, popularity_margin) {
+                (Some(_), _) => Some("--min-popularity"),
+                (_, Some(_)) => Some("--popularity-margin"),
+                _ => None,
+            };
+            if let Some(flag) = flag {
+                return Err(anyhow::anyhow!("'{flag}' can only be used with '--fix'"));
+            }
+        }
+
+        if patch.is_some() && check {
+            return Err(anyhow::anyhow!(
+                "'--diff' and '--patch' can't be used with `spelchek check`"
+            ));
+        }
+
+        if requested_paths.is_empty() {
+            return Err(anyhow::anyhow!("not enough arguments given"));
+        }
+        if patch.is_some() && requested_paths.iter().any(|path| *path == STDIN_PATH) {
+            return Err(anyhow::anyhow!(
+                "'--diff' and '--patch' can't be used with text read from stdin"
+            ));
+        }
+        if requested_paths.len() > 1 && requested_paths.iter().any(|path| *path == STDIN_PATH) {
+            return Err(anyhow::anyhow!(
+                "'{STDIN_PATH}' (stdin) can't be checked together with other paths"
+            ));
+        }
+
+        let requested_paths = requested_paths
+            .into_iter()
+            .map(|path| match path.as_str() {
+                STDIN_PATH => Ok(PathBuf::from(STDIN_PATH)),
+                path => canonicalize(path).with_context(|| format!("couldn't open {path}")),
+            })
+            .collect::<Result<Vec<PathBuf>>>()?;
+
+        let mut config = Self {
+            command: match (check, fix) {
+                (true, _) => Command::Check,
+                (_, true) => Command::Fix,
+                _ => Command::Interactive,
+            },
+            spellchecked_paths: requested_paths,
+            filter,
+            format,
+            po_check_msgid,
+            po_source_lang,
+            output_format: output_format.unwrap_or_default(),
+            threshold: threshold.unwrap_or_default(),
+            confidence: Confidence::default(),
+            patch,
+            limits: SuggestionLimits::default(),
+            formats: BTreeMap::new(),
+            tokenizers: TokenizerRegistry::default(),
+            locations,
         };
+        config.apply_settings(min_popularity, popularity_margin)?;
+        Ok(config)
+    }
 
-        let requested_file_path = canonicalize(requested_file_path)?;
+    /// Reads the configuration files, and uses their settings where no option was passed on the
+    /// command line. The project's file is looked for from the checked paths, or from the working
+    /// directory if there are none. The settings apply to every checked file, so paths whose
+    /// projects have different files are rejected.
+    fn apply_settings(
+        &mut self,
+        min_popularity: Option<i64>,
+        popularity_margin: Option<f64>,
+    ) -> Result<()> {
+        let start_of = |path: &Path| -> Result<PathBuf> {
+            Ok(match path {
+                path if path.is_dir() => path.to_path_buf(),
+                path if path.as_os_str() != STDIN_PATH => {
+                    path.parent().unwrap_or(path).to_path_buf()
+                }
+                _ => env::current_dir()?,
+            })
+        };
+        let start = match self.spellchecked_paths.first() {
+            Some(path) => start_of(path)?,
+            None => env::current_dir()?,
+        };
 
-        Ok(Self {
-            spellchecked_file_path: requested_file_path,
-        })
+        let project_file = find_upwards(&start, CONFIG_FILE_NAME);
+        for path in self.spellchecked_paths.iter().skip(1) {
+            let other_file = find_upwards(&start_of(path)?, CONFIG_FILE_NAME);
+            if other_file != project_file {
+                let describe = |file: &Option<PathBuf>| match file {
+                    Some(file) => file.display().to_string(),
+                    None => format!("no {CONFIG_FILE_NAME}"),
+                };
+                return Err(anyhow::anyhow!(
+                    "{} and {} have different settings ({} and {}), check them separately",
+                    self.spellchecked_paths[0].display(),
+                    path.display(),
+                    describe(&project_file),
+                    describe(&other_file)
+                ));
+            }
+        }
+        let user_config = self
+            .locations
+            .user_dir()
+            .ok()
+            .map(|dir| dir.join(USER_CONFIG_FILE_NAME));
+        let settings = Settings::discover(&start, user_config.as_deref())?;
+
+        let locations = &mut self.locations;
+        locations.dictionary = locations.dictionary.take().or(settings.lang);
+        locations.dict = locations.dict.take().or(settings.dict);
+        locations.forbidden = locations.forbidden.take().or(settings.forbidden);
+        locations.replacements = locations.replacements.take().or(settings.replacements);
+        locations.phrases = locations.phrases.take().or(settings.phrases);
+        locations.corrections = locations.corrections.take().or(settings.corrections);
+        self.po_source_lang = self.po_source_lang.take().or(settings.po_source_lang);
+
+        if self.filter.include.is_empty() {
+            self.filter.include = settings.include;
+        }
+        self.filter.exclude.splice(0..0, settings.exclude);
+        self.formats = settings.formats;
+
+        self.confidence = Confidence {
+            min_popularity: min_popularity
+                .or(settings.min_popularity)
+                .unwrap_or(Confidence::default().min_popularity),
+            popularity_margin: popularity_margin.or(settings.popularity_margin),
+        };
+        self.limits = SuggestionLimits {
+            count: settings.suggestions.unwrap_or(self.limits.count),
+            max_distance: settings.max_distance,
+        };
+        Ok(())
     }
 
-    pub fn get_spellchecked_file_path(&self) -> &PathBuf {
-        &self.spellchecked_file_path
+    pub fn get_command(&self) -> &Command {
+        &self.command
     }
-}
 
-pub fn get_program_files_path() -> PathBuf {
-    Path::new(&home::home_dir().unwrap()).join(".spelchek")
-}
+    /// The files and directories to check, as given on the command line
+    pub fn get_spellchecked_paths(&self) -> &[PathBuf] {
+        &self.spellchecked_paths
+    }
+
+    /// Returns the files to check: the files given on the command line and the files found in the
+    /// directories
+    pub fn find_files(&self) -> Result<Vec<PathBuf>> {
+        files::find_files(&self.spellchecked_paths, &self.filter)
+    }
 
-pub fn run(config: &Config) -> Result<()> {
-    let mut terminal = start_terminal()?;
+    /// Whether the text is read from stdin rather than from files
+    pub fn reads_stdin(&self) -> bool {
+        self.spellchecked_paths
+            .iter()
+            .any(|path| path.as_os_str() == STDIN_PATH)
+    }
 
-    initialize_panic_hook();
+    /// Reads the text from stdin
+    fn read_stdin(&self) -> Result<String> {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes)?;
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    }
+
+    pub fn get_locations(&self) -> &Locations {
+        &self.locations
+    }
 
-    let path = config.get_spellchecked_file_path().clone();
-    let file_contents: String = String::from_utf8_lossy(&fs::read(&path)?).to_string();
-    let mut app = AppState::new(path, file_contents)?;
+    /// How many suggestions are offered, and how far from the misspelling they can be
+    pub fn get_suggestion_limits(&self) -> SuggestionLimits {
+        self.limits
+    }
+
+    /// Loads the spellchecker of the checked files. If the source strings of translation files
+    /// are checked and are in another language than the translations, it gets the spellchecker of
+    /// that language too: the dictionary chosen with `--po-source-lang`, or the default one.
+    pub fn load_spellchecker(&self) -> Result<Spellchecker> {
+        let mut spellchecker = Spellchecker::load(&self.locations)?;
+        spellchecker.set_suggestion_limits(self.limits);
+
+        let source_lang = self.po_source_lang.as_deref().unwrap_or(DEFAULT_DICTIONARY);
+        let same_language =
+            source_lang == self.locations.dictionary_name() && self.locations.dict.is_none();
+        if self.checks_msgid() && !same_language {
+            let mut source = Spellchecker::load(&self.locations.of_dictionary(source_lang))
+                .with_context(|| {
+                    format!("couldn't load the '{source_lang}' dictionary for the source strings")
+                })?;
+            source.set_suggestion_limits(self.limits);
+            spellchecker.set_source_language(source);
+        }
+        Ok(spellchecker)
+    }
+
+    /// Whether the source strings of translation files are checked, with `--po-check-msgid` or
+    /// with the `po-msgid` format
+    fn checks_msgid(&self) -> bool {
+        self.po_check_msgid
+            || self.format.as_deref() == Some("po-msgid")
+            || self.formats.values().any(|name| name == "po-msgid")
+    }
+
+    /// The available document formats. Library users can register their own tokenizers here
+    /// before calling [`run`].
+    pub fn tokenizers_mut(&mut self) -> &mut TokenizerRegistry {
+        &mut self.tokenizers
+    }
+
+    /// Returns the tokenizer for a spellchecked file - the one selected with `--format`, the one
+    /// the settings choose for the file's extension, or the one registered for it.
+    pub fn tokenizer_for(&self, path: &Path) -> Result<Arc<dyn Tokenizer>> {
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        let configured = extension
+            .and_then(|extension| Some((extension, self.formats.get(extension)?.as_str())));
+        let (name, setting) = match (&self.format, configured) {
+            (Some(name), _) => (name.as_str(), None),
+            (None, Some((extension, name))) => (name, Some(format!("formats.{extension}"))),
+            (None, None) => match self.tokenizers.name_for_path(path) {
+                Some(name) => (name, None),
+                None => return Ok(self.tokenizers.for_path(path)),
+            },
+        };
+        let name = match name {
+            "po" if self.po_check_msgid => "po-msgid",
+            name => name,
+        };
+
+        self.tokenizers.get(name).ok_or_else(|| {
+            // Formats chosen in the configuration files are only checked here, once the library
+            // users' own formats are registered
+            let prefix = setting.map_or(String::new(), |key| format!("`{key}`: "));
+            anyhow::anyhow!(
+                "{prefix}unknown format '{name}' (available formats: {})",
+                self.tokenizers.names().join(", ")
+            )
+        })
+    }
+}
+
+/// Runs the command, returning the code the program should exit with. Errors are meant to be
+/// reported with [`EXIT_ERROR`].
+pub fn run(config: &Config) -> Result<ExitCode> {
+    match config.get_command() {
+        Command::Dict(command) => {
+            return command
+                .run(config.get_locations())
+                .map(|_| ExitCode::SUCCESS)
+        }
+        Command::Eval(command) => {
+            return command
+                .run(config.get_locations())
+                .map(|_| ExitCode::SUCCESS)
+        }
+        Command::Pipe(command) => {
+            return command
+                .run(config.get_locations(), config.get_suggestion_limits())
+                .map(|_| ExitCode::SUCCESS)
+        }
+        Command::Lsp(command) => return command.run(config).map(|_| ExitCode::SUCCESS),
+        Command::Check => return check_without_tui(config),
+        Command::Fix => return fix_without_tui(config),
+        Command::Interactive => {}
+    }
+
+    // Everything that can fail is loaded before the terminal is taken over, so that the errors
+    // are readable
+    let mut spellchecker = config.load_spellchecker()?;
+    let mut app = match config.reads_stdin() {
+        true => {
+            let text = config.read_stdin()?;
+            spellchecker.load_project_words(&env::current_dir()?.join(STDIN_NAME))?;
+            let mut app =
+                AppState::with_spellchecker(PathBuf::from(STDIN_PATH), text, spellchecker)?;
+            app.tokenizer = config.tokenizer_for(Path::new(STDIN_PATH))?;
+            app.save_to_stdout();
+            app
+        }
+        false => open_files(config, spellchecker)?,
+    };
+    if let Some(target) = &config.patch {
+        app.save_to_patch(target.clone());
+    }
     app.check_spelling();
 
+    // Stdout can't show the TUI when it is piped somewhere, like when filtering text from an
+    // editor, so the terminal is then opened directly
+    let mut terminal = start_terminal(config.reads_stdin() || !io::stdout().is_terminal())?;
+
+    initialize_panic_hook();
+
     while !app.should_quit() {
         terminal.draw(|frame| render::render(frame, &mut app))?;
         update::update(&mut app)?;
     }
 
-    close_terminal()?;
     terminal.show_cursor()?;
+    close_terminal()?;
+    if let Some(text) = app.take_stdout_text() {
+        let mut out = io::stdout().lock();
+        out.write_all(text.as_bytes())?;
+        out.flush()?;
+    }
+    write_diff(&mut app)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Writes the diff of the saved corrections to stdout, if it was asked for with `--diff`
+fn write_diff(app: &mut AppState) -> Result<()> {
+    if let Some(patch) = app.take_patch() {
+        if *patch.get_target() == PatchTarget::Stdout {
+            let mut out = io::stdout().lock();
+            patch.write(&mut out)?;
+            out.flush()?;
+        }
+    }
     Ok(())
 }
 
-fn start_terminal() -> Result<Terminal<CrosstermBackend<impl Write>>> {
+/// Checks all of the files to be stepped through in the TUI, and opens them starting with the
+/// first one
+fn open_files(config: &Config, mut spellchecker: Spellchecker) -> Result<AppState> {
+    let files = config.find_files()?;
+    let checked = files::check_files(
+        &files,
+        &mut spellchecker,
+        &|path| config.tokenizer_for(path),
+        &|_, file| file,
+    )?;
+    let mut checked = checked.into_iter();
+    let first = checked
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("there are no files to check"))?;
+
+    spellchecker.load_project_words(&first.path)?;
+    let mut app = AppState::with_spellchecker(first.path, first.text, spellchecker)?;
+    app.tokenizer = first.tokenizer;
+    for file in checked {
+        let count = file.misspellings.len();
+        app.add_file(file.path, file.text, file.tokenizer, count);
+    }
+    Ok(app)
+}
+
+/// Checks the files and prints their misspellings, never touching the terminal's modes. Fails with
+/// [`EXIT_MISSPELLINGS`] if there are more misspellings than allowed.
+fn check_without_tui(config: &Config) -> Result<ExitCode> {
+    let mut spellchecker = config.load_spellchecker()?;
+
+    // The paths are shown relative to the working directory, like compilers do
+    let current_dir = env::current_dir().ok();
+    let shown_path = |path: &Path| -> PathBuf {
+        match &current_dir {
+            Some(dir) => path.strip_prefix(dir).unwrap_or(path).to_path_buf(),
+            None => path.to_path_buf(),
+        }
+    };
+
+    let (misspellings, files) = match config.reads_stdin() {
+        true => {
+            let text = config.read_stdin()?;
+            spellchecker.load_project_words(&env::current_dir()?.join(STDIN_NAME))?;
+            let tokenizer = config.tokenizer_for(Path::new(STDIN_PATH))?;
+            let found = spellchecker.find_misspellings(&text, tokenizer.as_ref());
+            let path = Path::new(STDIN_NAME);
+            (report::collect(path, &text, &spellchecker, found), 1)
+        }
+        false => {
+            let files = config.find_files()?;
+            let reports = files::check_files(
+                &files,
+                &mut spellchecker,
+                &|path| config.tokenizer_for(path),
+                &|spellchecker, file| {
+                    let path = shown_path(&file.path);
+                    report::collect(&path, &file.text, spellchecker, file.misspellings)
+                },
+            )?;
+            // Binary files are skipped, and aren't counted as checked
+            let checked = reports.len();
+            (reports.concat(), checked)
+        }
+    };
+
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    report::write(&mut out, config.output_format, &misspellings, files)?;
+    out.flush()?;
+
+    match config.threshold.fails(misspellings.len()) {
+        true => Ok(ExitCode::from(EXIT_MISSPELLINGS)),
+        false => Ok(ExitCode::SUCCESS),
+    }
+}
+
+/// Corrects the misspellings which have an unambiguous correction, saving the files (or writing
+/// the text to stdout, if it was read from stdin, or the diff, with `--diff` or `--patch`) and
+/// reporting what was changed and what was left for review. Fails with [`EXIT_MISSPELLINGS`] if
+/// any misspellings were left.
+fn fix_without_tui(config: &Config) -> Result<ExitCode> {
+    let mut spellchecker = config.load_spellchecker()?;
+
+    if config.reads_stdin() {
+        let text = config.read_stdin()?;
+        spellchecker.load_project_words(&env::current_dir()?.join(STDIN_NAME))?;
+        let mut app = AppState::with_spellchecker(PathBuf::from(STDIN_PATH), text, spellchecker)?;
+        app.tokenizer = config.tokenizer_for(Path::new(STDIN_PATH))?;
+        app.save_to_stdout();
+        app.check_spelling();
+
+        let fixed = fix::fix_file(&mut app, config.confidence);
+        app.save_file()?;
+        let text = app.take_stdout_text().unwrap_or_default();
+        let mut out = io::stdout().lock();
+        out.write_all(text.as_bytes())?;
+        out.flush()?;
+
+        // Stdout holds the text, so the report goes to stderr
+        let mut report = io::stderr().lock();
+        fix::write_report(&mut report, Path::new(STDIN_NAME), &text, &fixed)?;
+        let left = app.spellchecker.misspellings().len();
+        fix::write_summary(&mut report, fixed.len() - left, left)?;
+        return Ok(match left {
+            0 => ExitCode::SUCCESS,
+            _ => ExitCode::from(EXIT_MISSPELLINGS),
+        });
+    }
+
+    let mut app = open_files(config, spellchecker)?;
+    if let Some(target) = &config.patch {
+        app.save_to_patch(target.clone());
+    }
+    app.check_spelling();
+    // The report goes to stderr when stdout holds the diff
+    let mut out: Box<dyn Write> = match config.patch {
+        Some(PatchTarget::Stdout) => Box::new(io::stderr().lock()),
+        _ => Box::new(io::BufWriter::new(io::stdout().lock())),
+    };
+    let (mut fixed_count, mut left) = (0, 0);
+    for i in 0..app.file_count() {
+        app.select_file(i)?;
+        let fixed = fix::fix_file(&mut app, config.confidence);
+        let path = app.get_file_path().clone();
+        let shown_path = match env::current_dir() {
+            Ok(dir) => path.strip_prefix(dir).unwrap_or(&path).to_path_buf(),
+            Err(_) => path,
+        };
+        fix::write_report(&mut out, &shown_path, app.get_buffer(), &fixed)?;
+        left += app.spellchecker.misspellings().len();
+        fixed_count += fixed.len() - app.spellchecker.misspellings().len();
+    }
+    app.save_all_files()?;
+    fix::write_summary(&mut out, fixed_count, left)?;
+    out.flush()?;
+    write_diff(&mut app)?;
+
+    match left {
+        0 => Ok(ExitCode::SUCCESS),
+        _ => Ok(ExitCode::from(EXIT_MISSPELLINGS)),
+    }
+}
+
+/// Takes over the terminal for the TUI, drawing it on [`TTY_PATH`] instead of stdout if `on_tty`
+fn start_terminal(on_tty: bool) -> Result<Terminal<CrosstermBackend<Box<dyn Write>>>> {
+    let mut output = terminal_output(on_tty)?;
+    TERMINAL_STARTED.store(true, Ordering::SeqCst);
+    TERMINAL_ON_TTY.store(on_tty, Ordering::SeqCst);
     enable_raw_mode()?;
-    execute!(io::stdout(), EnterAlternateScreen)?;
+    execute!(output, EnterAlternateScreen)?;
 
-    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(output))?;
     terminal.clear()?;
 
     Ok(terminal)
 }
 
+/// Restores the terminal, if the TUI has taken it over
 pub fn close_terminal() -> Result<()> {
+    if !TERMINAL_STARTED.swap(false, Ordering::SeqCst) {
+        return Ok(());
+    }
     disable_raw_mode()?;
-    execute!(io::stdout(), LeaveAlternateScreen)?;
+    let mut output = terminal_output(TERMINAL_ON_TTY.load(Ordering::SeqCst))?;
+    execute!(output, LeaveAlternateScreen)?;
     Ok(())
 }
 
+/// Returns where the TUI is drawn: stdout, or the terminal itself if `on_tty`
+fn terminal_output(on_tty: bool) -> Result<Box<dyn Write>> {
+    if !on_tty {
+        return Ok(Box::new(io::stdout()));
+    }
+    let tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(TTY_PATH)
+        .map_err(|error| anyhow::anyhow!("couldn't open the terminal ({TTY_PATH}): {error}"))?;
+    Ok(Box::new(tty))
+}
+
 fn initialize_panic_hook() {
     let original_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
@@ -0,0 +1,741 @@
+//! The `lsp` subcommand, a Language Server Protocol server spellchecking the documents opened in
+//! an editor.
+//!
+//! ```text
+//! spelchek lsp [--stdio]
+//! ```
+//!
+//! The server speaks over stdio. The misspellings of every open document are published as
+//! diagnostics, and checked again on every change - only the lines touched by the change are
+//! checked again. Code actions replace a misspelling with one of its suggestions, add the word to
+//! the user's word list (the `spelchek.addWord` command) or ignore it until the server exits (the
+//! `spelchek.ignoreWord` command).
+//!
+//! The documents are checked like the files of `spelchek check`: with the formats chosen in the
+//! settings, and each with the project word list found by walking up from it.
+
+use std::collections::HashMap;
+use std::ops;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationMethod, PublishDiagnostics,
+};
+use lsp_types::request::{CodeActionRequest, ExecuteCommand, Request as RequestMethod};
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams,
+    CodeActionProviderCapability, Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, ExecuteCommandOptions,
+    ExecuteCommandParams, NumberOrString, Position, PublishDiagnosticsParams, Range,
+    ServerCapabilities, TextDocumentContentChangeEvent, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextEdit, Url, WorkspaceEdit,
+};
+
+use crate::app_state::correction_for;
+use crate::paths::project_words_path;
+use crate::prelude::*;
+use crate::report::{kind_name, LineIndex};
+use crate::spellchecker::layers::LayerKind;
+use crate::spellchecker::tokenizer::Tokenizer;
+use crate::spellchecker::{Misspelling, MisspellingKind, Spellchecker};
+use crate::Config;
+
+/// The command adding a word to the user's word list
+const ADD_WORD_COMMAND: &str = "spelchek.addWord";
+/// The command ignoring a word until the server exits
+const IGNORE_WORD_COMMAND: &str = "spelchek.ignoreWord";
+/// The name of the server in the diagnostics
+const SOURCE: &str = "spelchek";
+
+#[derive(Debug, PartialEq)]
+pub struct LspCommand;
+
+impl LspCommand {
+    /// Parses the arguments following `lsp`
+    pub fn parse(args: &[String]) -> Result<Self> {
+        match args.iter().find(|arg| *arg != "--stdio") {
+            Some(arg) => Err(anyhow!("unexpected argument '{arg}'")),
+            None => Ok(LspCommand),
+        }
+    }
+
+    pub fn run(&self, config: &Config) -> Result<()> {
+        // Everything that can fail is loaded before the client is answered
+        let spellchecker = config.load_spellchecker()?;
+
+        let (connection, io_threads) = Connection::stdio();
+        let capabilities = ServerCapabilities {
+            text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                TextDocumentSyncKind::INCREMENTAL,
+            )),
+            code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+            execute_command_provider: Some(ExecuteCommandOptions {
+                commands: vec![
+                    ADD_WORD_COMMAND.to_string(),
+                    IGNORE_WORD_COMMAND.to_string(),
+                ],
+                work_done_progress_options: Default::default(),
+            }),
+            ..Default::default()
+        };
+        connection.initialize(serde_json::to_value(capabilities)?)?;
+
+        let tokenizer_for = |path: &Path| config.tokenizer_for(path);
+        let mut server = Server::new(spellchecker, &tokenizer_for);
+        serve(&connection, &mut server)?;
+
+        drop(connection);
+        io_threads.join()?;
+        Ok(())
+    }
+}
+
+/// Answers the messages of the client until it shuts the server down
+fn serve(connection: &Connection, server: &mut Server) -> Result<()> {
+    for message in &connection.receiver {
+        let changed = match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                let (response, changed) = server.handle_request(request);
+                connection.sender.send(response.into())?;
+                changed
+            }
+            Message::Notification(notification) => server.handle_notification(notification)?,
+            Message::Response(_) => Vec::new(),
+        };
+
+        for uri in changed {
+            let params = PublishDiagnosticsParams {
+                diagnostics: server.diagnostics(&uri),
+                uri,
+                version: None,
+            };
+            let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+            connection.sender.send(notification.into())?;
+        }
+    }
+    Ok(())
+}
+
+/// A document opened in the editor
+#[derive(Debug)]
+struct Document {
+    path: PathBuf, // Where the project word list is looked for from
+    text: String,
+    tokenizer: Arc<dyn Tokenizer>,
+    misspellings: Vec<Misspelling>, // Sorted by their positions
+}
+
+impl Document {
+    /// Applies a change made in the editor, checking the lines it touches (and the phrases
+    /// continuing from or onto them) again
+    fn apply(&mut self, change: TextDocumentContentChangeEvent, spellchecker: &Spellchecker) {
+        let Some(range) = change.range else {
+            self.text = change.text;
+            self.misspellings = spellchecker.find_misspellings(&self.text, self.tokenizer.as_ref());
+            return;
+        };
+
+        let start = offset_at(&self.text, range.start);
+        let end = offset_at(&self.text, range.end).max(start);
+        let (mut region_start, mut old_region_end) =
+            (line_start(&self.text, start), line_end(&self.text, end));
+        // A phrase can continue on the next lines, accepting the words there, so the lines around
+        // the change which a phrase could span are checked again too
+        for _ in 1..spellchecker.phrases().max_word_count() {
+            region_start = line_start(&self.text, region_start.saturating_sub(1));
+            old_region_end = line_end(&self.text, (old_region_end + 1).min(self.text.len()));
+        }
+
+        self.text.replace_range(start..end, &change.text);
+        let offset = change.text.len() as i32 - (end - start) as i32;
+        let region = region_start..(old_region_end as i32 + offset) as usize;
+
+        let (before, rest): (Vec<Misspelling>, Vec<Misspelling>) =
+            std::mem::take(&mut self.misspellings)
+                .into_iter()
+                .partition(|misspelling| misspelling.get_end() < region_start);
+        let after = rest
+            .into_iter()
+            .filter(|misspelling| misspelling.get_start() >= old_region_end)
+            .map(|mut misspelling| {
+                misspelling.offset(offset);
+                misspelling
+            });
+        let tokenizer = Within {
+            tokenizer: self.tokenizer.as_ref(),
+            range: region,
+        };
+        let found = spellchecker.find_misspellings(&self.text, &tokenizer);
+
+        self.misspellings = before.into_iter().chain(found).chain(after).collect();
+    }
+}
+
+/// Only the words of a part of the text, keeping the context of the whole text for the tokenizer
+#[derive(Debug)]
+struct Within<'a> {
+    tokenizer: &'a dyn Tokenizer,
+    range: ops::Range<usize>,
+}
+
+impl Tokenizer for Within<'_> {
+    fn tokenize(&self, buffer: &str) -> Vec<ops::Range<usize>> {
+        let mut words = self.tokenizer.tokenize(buffer);
+        words.retain(|word| word.start >= self.range.start && word.end <= self.range.end);
+        words
+    }
+
+    fn tokenize_source(&self, buffer: &str) -> Vec<ops::Range<usize>> {
+        let mut words = self.tokenizer.tokenize_source(buffer);
+        words.retain(|word| word.start >= self.range.start && word.end <= self.range.end);
+        words
+    }
+}
+
+/// The state of the server: the open documents and the spellchecker checking them
+struct Server<'a> {
+    spellchecker: Spellchecker,
+    tokenizer_for: &'a dyn Fn(&Path) -> Result<Arc<dyn Tokenizer>>,
+    project: Option<PathBuf>, // The project word list loaded into the spellchecker
+    documents: HashMap<Url, Document>,
+}
+
+impl<'a> Server<'a> {
+    fn new(
+        spellchecker: Spellchecker,
+        tokenizer_for: &'a dyn Fn(&Path) -> Result<Arc<dyn Tokenizer>>,
+    ) -> Self {
+        Server {
+            spellchecker,
+            tokenizer_for,
+            project: None,
+            documents: HashMap::new(),
+        }
+    }
+
+    /// Loads the project word list of the document at `path` into the spellchecker, unless it is
+    /// the one already loaded
+    fn use_project_of(&mut self, path: &Path) -> Result<()> {
+        let project = project_words_path(path);
+        if self.project.as_ref() != Some(&project) {
+            self.spellchecker.load_project_words(path)?;
+            self.project = Some(project);
+        }
+        Ok(())
+    }
+
+    /// Handles a notification, returning the documents whose diagnostics have changed
+    fn handle_notification(&mut self, notification: Notification) -> Result<Vec<Url>> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let document = params.text_document;
+                self.open(document.uri.clone(), document.text)?;
+                Ok(vec![document.uri])
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.change(&uri, params.content_changes)?;
+                Ok(vec![uri])
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                Ok(vec![params.text_document.uri])
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Answers a request, also returning the documents whose diagnostics have changed
+    fn handle_request(&mut self, request: Request) -> (Response, Vec<Url>) {
+        let id = request.id.clone();
+        let invalid = |error: serde_json::Error| {
+            Response::new_err(
+                id.clone(),
+                ErrorCode::InvalidParams as i32,
+                error.to_string(),
+            )
+        };
+
+        match request.method.as_str() {
+            CodeActionRequest::METHOD => {
+                match serde_json::from_value::<CodeActionParams>(request.params) {
+                    Ok(params) => {
+                        match self.code_actions(&params.text_document.uri, params.range) {
+                            Ok(actions) => (Response::new_ok(id, actions), Vec::new()),
+                            Err(error) => (
+                                Response::new_err(
+                                    id,
+                                    ErrorCode::InternalError as i32,
+                                    format!("{error:#}"),
+                                ),
+                                Vec::new(),
+                            ),
+                        }
+                    }
+                    Err(error) => (invalid(error), Vec::new()),
+                }
+            }
+            ExecuteCommand::METHOD => {
+                match serde_json::from_value::<ExecuteCommandParams>(request.params) {
+                    Ok(params) => match self.execute(&params.command, &params.arguments) {
+                        Ok(()) => (
+                            Response::new_ok(id, serde_json::Value::Null),
+                            self.documents.keys().cloned().collect(),
+                        ),
+                        Err(error) => (
+                            Response::new_err(
+                                id,
+                                ErrorCode::InvalidParams as i32,
+                                format!("{error:#}"),
+                            ),
+                            Vec::new(),
+                        ),
+                    },
+                    Err(error) => (invalid(error), Vec::new()),
+                }
+            }
+            method => (
+                Response::new_err(
+                    id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("unsupported request '{method}'"),
+                ),
+                Vec::new(),
+            ),
+        }
+    }
+
+    fn open(&mut self, uri: Url, text: String) -> Result<()> {
+        let path = uri
+            .to_file_path()
+            .unwrap_or_else(|_| PathBuf::from(uri.path()));
+        let tokenizer = (self.tokenizer_for)(&path)?;
+        self.use_project_of(&path)?;
+        let misspellings = self
+            .spellchecker
+            .find_misspellings(&text, tokenizer.as_ref());
+        let document = Document {
+            path,
+            text,
+            tokenizer,
+            misspellings,
+        };
+        self.documents.insert(uri, document);
+        Ok(())
+    }
+
+    fn change(&mut self, uri: &Url, changes: Vec<TextDocumentContentChangeEvent>) -> Result<()> {
+        let Some(path) = self
+            .documents
+            .get(uri)
+            .map(|document| document.path.clone())
+        else {
+            return Ok(());
+        };
+        self.use_project_of(&path)?;
+        if let Some(document) = self.documents.get_mut(uri) {
+            for change in changes {
+                document.apply(change, &self.spellchecker);
+            }
+        }
+        Ok(())
+    }
+
+    fn diagnostics(&self, uri: &Url) -> Vec<Diagnostic> {
+        let Some(document) = self.documents.get(uri) else {
+            return Vec::new();
+        };
+        let lines = LineIndex::new(&document.text);
+        document
+            .misspellings
+            .iter()
+            .map(|misspelling| Diagnostic {
+                range: range_of(&lines, misspelling),
+                severity: Some(match misspelling.get_kind().is_style_issue() {
+                    true => DiagnosticSeverity::WARNING,
+                    false => DiagnosticSeverity::INFORMATION,
+                }),
+                code: Some(NumberOrString::String(
+                    kind_name(misspelling.get_kind()).to_string(),
+                )),
+                source: Some(SOURCE.to_string()),
+                message: message(misspelling),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// The code actions for the misspellings in the range: replacing them with one of their
+    /// suggestions, adding them to the dictionary and ignoring them.
+    fn code_actions(&mut self, uri: &Url, range: Range) -> Result<Vec<CodeActionOrCommand>> {
+        let Some(path) = self
+            .documents
+            .get(uri)
+            .map(|document| document.path.clone())
+        else {
+            return Ok(Vec::new());
+        };
+        // The project's words are suggested too
+        self.use_project_of(&path)?;
+        let document = self
+            .documents
+            .get_mut(uri)
+            .expect("the document was just found");
+        let start = offset_at(&document.text, range.start);
+        let end = offset_at(&document.text, range.end);
+        let lines = LineIndex::new(&document.text);
+
+        let mut actions = Vec::new();
+        for i in 0..document.misspellings.len() {
+            let misspelling = &mut document.misspellings[i];
+            if misspelling.get_start() > end || misspelling.get_end() + 1 < start {
+                continue;
+            }
+            self.spellchecker.suggest_misspelling(misspelling);
+
+            let misspelling = &document.misspellings[i];
+            let word = misspelling.get_word();
+            let range = range_of(&lines, misspelling);
+            for (n, suggestion) in misspelling.get_suggestions().iter().enumerate() {
+                let correction = correction_for(word, suggestion);
+                let edit = TextEdit {
+                    range,
+                    new_text: correction.clone(),
+                };
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: format!("Change to '{correction}'"),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+                        ..Default::default()
+                    }),
+                    is_preferred: Some(n == 0),
+                    ..Default::default()
+                }));
+            }
+
+            // The style guide can't be overridden with the word lists
+            if misspelling.get_kind().is_style_issue() {
+                continue;
+            }
+            let commands = [
+                (ADD_WORD_COMMAND, format!("Add '{word}' to the dictionary")),
+                (IGNORE_WORD_COMMAND, format!("Ignore '{word}'")),
+            ];
+            for (command, title) in commands {
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: title.clone(),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    command: Some(lsp_types::Command {
+                        title,
+                        command: command.to_string(),
+                        arguments: Some(vec![serde_json::Value::String(word.clone())]),
+                    }),
+                    ..Default::default()
+                }));
+            }
+        }
+        Ok(actions)
+    }
+
+    /// Executes one of the server's commands, checking all of the documents again
+    fn execute(&mut self, command: &str, arguments: &[serde_json::Value]) -> Result<()> {
+        let layer = match command {
+            ADD_WORD_COMMAND => LayerKind::User,
+            IGNORE_WORD_COMMAND => LayerKind::Session,
+            _ => return Err(anyhow!("unknown command '{command}'")),
+        };
+        let word = match arguments.first() {
+            Some(serde_json::Value::String(word)) => word,
+            _ => return Err(anyhow!("'{command}' requires a word")),
+        };
+        self.spellchecker.add_word(layer, word)?;
+
+        let uris: Vec<Url> = self.documents.keys().cloned().collect();
+        for uri in uris {
+            let path = self.documents[&uri].path.clone();
+            self.use_project_of(&path)?;
+            let document = self
+                .documents
+                .get_mut(&uri)
+                .expect("the documents aren't changed");
+            document.misspellings = self
+                .spellchecker
+                .find_misspellings(&document.text, document.tokenizer.as_ref());
+        }
+        Ok(())
+    }
+}
+
+/// The message of the diagnostic of a misspelling
+fn message(misspelling: &Misspelling) -> String {
+    let word = misspelling.get_word();
+    let suggestions = misspelling.get_suggestions().join("', '");
+    match misspelling.get_kind() {
+        MisspellingKind::Spelling => format!("'{word}' is misspelled"),
+        MisspellingKind::Capitalization => {
+            format!("'{word}' is written in the wrong case, did you mean '{suggestions}'?")
+        }
+        MisspellingKind::Forbidden => format!("'{word}' is forbidden by the style guide"),
+        MisspellingKind::Replacement => {
+            format!("the style guide prefers '{suggestions}' to '{word}'")
+        }
+    }
+}
+
+/// The range of a misspelling in the editor's positions
+fn range_of(lines: &LineIndex, misspelling: &Misspelling) -> Range {
+    Range {
+        start: position_at(lines, misspelling.get_start()),
+        end: position_at(lines, misspelling.get_end() + 1),
+    }
+}
+
+/// The position of a byte offset in the text, with the characters counted in UTF-16 code units
+/// like the protocol does
+fn position_at(lines: &LineIndex, offset: usize) -> Position {
+    let (line, character) = lines.utf16_position(offset);
+    Position::new(line as u32, character as u32)
+}
+
+/// The byte offset of a position in the text. Positions past the end of a line or of the text
+/// are moved to its end.
+fn offset_at(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(newline) => line_start += newline + 1,
+            None => return text.len(),
+        }
+    }
+
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= position.character as usize || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// The offset of the start of the line containing the offset
+fn line_start(text: &str, offset: usize) -> usize {
+    text[..offset].rfind('\n').map_or(0, |newline| newline + 1)
+}
+
+/// The offset of the end of the line containing the offset (of its newline)
+fn line_end(text: &str, offset: usize) -> usize {
+    text[offset..]
+        .find('\n')
+        .map_or(text.len(), |newline| offset + newline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::PROJECT_WORDS_FILE_NAME;
+    use crate::spellchecker::phrases::PhraseList;
+    use crate::spellchecker::tokenizer::TokenizerRegistry;
+    use crate::test_support::{test_dictionary, test_dir};
+    use std::fs;
+
+    fn built_in_tokenizer(path: &Path) -> Result<Arc<dyn Tokenizer>> {
+        Ok(TokenizerRegistry::default().for_path(path))
+    }
+
+    fn server() -> Server<'static> {
+        let dict = test_dictionary();
+        Server::new(Spellchecker::with_dictionary(dict), &built_in_tokenizer)
+    }
+
+    fn uri() -> Url {
+        Url::parse("file:///notes.txt").unwrap()
+    }
+
+    fn range(start: (u32, u32), end: (u32, u32)) -> Range {
+        Range {
+            start: Position::new(start.0, start.1),
+            end: Position::new(end.0, end.1),
+        }
+    }
+
+    /// The misspelled words with their ranges
+    fn misspellings(server: &Server) -> Vec<(String, Range)> {
+        let document = &server.documents[&uri()];
+        server
+            .diagnostics(&uri())
+            .into_iter()
+            .zip(&document.misspellings)
+            .map(|(diagnostic, misspelling)| (misspelling.get_word().clone(), diagnostic.range))
+            .collect()
+    }
+
+    #[test]
+    fn test_positions() {
+        let text = "ab\nżółw 😀 x\n";
+        let lines = LineIndex::new(text);
+        assert_eq!(position_at(&lines, 0), Position::new(0, 0));
+        assert_eq!(position_at(&lines, 3), Position::new(1, 0));
+        let x = text.find('x').unwrap();
+        assert_eq!(position_at(&lines, x), Position::new(1, 8));
+        assert_eq!(offset_at(text, Position::new(1, 8)), x);
+        assert_eq!(offset_at(text, Position::new(0, 10)), 2);
+        assert_eq!(offset_at(text, Position::new(5, 0)), text.len());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let mut server = server();
+        server
+            .open(uri(), "this is sme\nżółw txet\n".to_string())
+            .unwrap();
+        assert_eq!(
+            misspellings(&server),
+            [
+                ("sme".to_string(), range((0, 8), (0, 11))),
+                ("żółw".to_string(), range((1, 0), (1, 4))),
+                ("txet".to_string(), range((1, 5), (1, 9)))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_incremental_changes() {
+        let mut server = server();
+        server
+            .open(uri(), "sme text\nthis is\ntxet\n".to_string())
+            .unwrap();
+
+        // Correcting a word and misspelling another on the same line
+        let change = |range, text: &str| TextDocumentContentChangeEvent {
+            range: Some(range),
+            range_length: None,
+            text: text.to_string(),
+        };
+        server
+            .change(&uri(), vec![change(range((0, 0), (0, 3)), "some tihs")])
+            .unwrap();
+        assert_eq!(
+            server.documents[&uri()].text,
+            "some tihs text\nthis is\ntxet\n"
+        );
+        assert_eq!(
+            misspellings(&server),
+            [
+                ("tihs".to_string(), range((0, 5), (0, 9))),
+                ("txet".to_string(), range((2, 0), (2, 4)))
+            ]
+        );
+
+        // Adding lines moves the misspellings after them
+        server
+            .change(&uri(), vec![change(range((1, 0), (1, 0)), "sme\nis\n")])
+            .unwrap();
+        assert_eq!(
+            misspellings(&server),
+            [
+                ("tihs".to_string(), range((0, 5), (0, 9))),
+                ("sme".to_string(), range((1, 0), (1, 3))),
+                ("txet".to_string(), range((4, 0), (4, 4)))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_changing_phrases_across_lines() {
+        let mut server = server();
+        server
+            .spellchecker
+            .set_phrases(PhraseList::from_source("ad hoc\n"));
+        server.open(uri(), "this is ad\nhoc\n".to_string()).unwrap();
+        assert!(misspellings(&server).is_empty());
+
+        // Breaking up the phrase on its first line leaves the second line's word misspelled
+        let change = TextDocumentContentChangeEvent {
+            range: Some(range((0, 8), (0, 10))),
+            range_length: None,
+            text: "ab".to_string(),
+        };
+        server.change(&uri(), vec![change]).unwrap();
+        assert_eq!(
+            misspellings(&server),
+            [
+                ("ab".to_string(), range((0, 8), (0, 10))),
+                ("hoc".to_string(), range((1, 0), (1, 3)))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_code_actions() {
+        let mut server = server();
+        server
+            .open(uri(), "this is sme\nsme\n".to_string())
+            .unwrap();
+
+        let actions = server.code_actions(&uri(), range((0, 9), (0, 9))).unwrap();
+        let titles: Vec<String> = actions
+            .iter()
+            .map(|action| match action {
+                CodeActionOrCommand::CodeAction(action) => action.title.clone(),
+                CodeActionOrCommand::Command(command) => command.title.clone(),
+            })
+            .collect();
+        assert_eq!(titles[0], "Change to 'some'");
+        assert!(titles.contains(&"Ignore 'sme'".to_string()));
+
+        let arguments = [serde_json::Value::String("sme".to_string())];
+        server.execute(IGNORE_WORD_COMMAND, &arguments).unwrap();
+        assert!(server.diagnostics(&uri()).is_empty());
+        assert!(server.execute("unknown", &arguments).is_err());
+    }
+
+    #[test]
+    fn test_project_words_of_each_document() {
+        let root = test_dir("lsp-projects");
+        for (project, word) in [("first", "sme"), ("second", "txet")] {
+            fs::create_dir_all(root.join(project)).unwrap();
+            fs::write(root.join(project).join(PROJECT_WORDS_FILE_NAME), word).unwrap();
+        }
+        let uri_in = |project: &str| Url::from_file_path(root.join(project).join("a.txt")).unwrap();
+
+        // Each document is checked with the word list of its own project
+        let mut server = server();
+        for project in ["first", "second"] {
+            server
+                .open(uri_in(project), "sme txet\n".to_string())
+                .unwrap();
+        }
+        let words = |server: &Server, project: &str| -> Vec<String> {
+            let document = &server.documents[&uri_in(project)];
+            let misspellings = document.misspellings.iter();
+            misspellings
+                .map(|misspelling| misspelling.get_word().clone())
+                .collect()
+        };
+        assert_eq!(words(&server, "first"), ["txet"]);
+        assert_eq!(words(&server, "second"), ["sme"]);
+
+        // And so is it after every document is checked again
+        let arguments = [serde_json::Value::String("tihs".to_string())];
+        server.execute(IGNORE_WORD_COMMAND, &arguments).unwrap();
+        assert_eq!(words(&server, "first"), ["txet"]);
+        assert_eq!(words(&server, "second"), ["sme"]);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
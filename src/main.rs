@@ -1,16 +1,19 @@
-use spelchek::prelude::*;
+use spelchek::EXIT_ERROR;
 
 use std::env;
+use std::process::ExitCode;
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     let args: Vec<String> = env::args().collect();
-    let config = spelchek::Config::build(&args[1..])?; // it is assumed that the first argument is
-                                                       // the program name, therefore we pass only the actual arguments
-    let res = spelchek::run(&config);
-    if let Result::Err(error) = res {
-        eprintln!("{error:?}");
-        spelchek::close_terminal()?;
-        std::process::exit(1);
+    // it is assumed that the first argument is the program name, therefore we pass only the
+    // actual arguments
+    let res = spelchek::Config::build(&args[1..]).and_then(|config| spelchek::run(&config));
+    match res {
+        Ok(code) => code,
+        Err(error) => {
+            let _ = spelchek::close_terminal();
+            eprintln!("{error:?}");
+            ExitCode::from(EXIT_ERROR)
+        }
     }
-    Ok(())
 }
@@ -0,0 +1,159 @@
+//! The `--diff` and `--patch` options, keeping the corrections as a unified diff against the
+//! original contents of the files instead of overwriting them, so that they can be reviewed (and
+//! later applied with `git apply` or `patch -p1`).
+//!
+//! The files are named in the diff relative to the working directory, prefixed with `a/` and `b/`
+//! like git does. Files outside of the working directory are named by their full path, without the
+//! prefixes.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use similar::TextDiff;
+
+use crate::prelude::*;
+
+/// How many unchanged lines are shown around the changed ones
+const CONTEXT_LINES: usize = 3;
+
+/// Where the diff is written
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchTarget {
+    Stdout,        // On exit (`--diff`)
+    File(PathBuf), // Every time the corrections are saved (`--patch <file>`)
+}
+
+/// A file's text before and after the saved corrections
+#[derive(Debug)]
+struct Change {
+    original: String,
+    corrected: String,
+}
+
+/// The saved corrections of all of the files
+#[derive(Debug)]
+pub struct Patch {
+    target: PatchTarget,
+    changes: BTreeMap<PathBuf, Change>,
+}
+
+impl Patch {
+    pub fn new(target: PatchTarget) -> Self {
+        Patch {
+            target,
+            changes: BTreeMap::new(),
+        }
+    }
+
+    pub fn get_target(&self) -> &PatchTarget {
+        &self.target
+    }
+
+    /// Records the corrected text of the file at `path`, replacing the one saved before
+    pub fn record(&mut self, path: &Path, original: &str, corrected: &str) {
+        match original == corrected {
+            true => self.changes.remove(path),
+            false => self.changes.insert(
+                path.to_path_buf(),
+                Change {
+                    original: original.to_string(),
+                    corrected: corrected.to_string(),
+                },
+            ),
+        };
+    }
+
+    /// Whether any of the files were changed
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Writes the diff of all of the changed files, in the order of their paths
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        let current_dir = env::current_dir().ok();
+        for (path, change) in &self.changes {
+            let shown = match &current_dir {
+                Some(dir) => path.strip_prefix(dir).unwrap_or(path),
+                None => path,
+            };
+            out.write_all(unified_diff(shown, &change.original, &change.corrected).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Writes the diff to the patch file, if it goes to one. The diff for stdout is written by the
+    /// caller on exit.
+    pub fn save(&self) -> Result<()> {
+        if let PatchTarget::File(path) = &self.target {
+            let mut diff = Vec::new();
+            self.write(&mut diff)?;
+            fs::write(path, diff)
+                .with_context(|| format!("couldn't write the patch to {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the unified diff turning `original` into `corrected`, for the file shown as `path`
+pub fn unified_diff(path: &Path, original: &str, corrected: &str) -> String {
+    let (old, new) = match path.is_relative() {
+        true => (
+            format!("a/{}", path.display()),
+            format!("b/{}", path.display()),
+        ),
+        false => (path.display().to_string(), path.display().to_string()),
+    };
+    TextDiff::from_lines(original, corrected)
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .header(&old, &new)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff() {
+        let original = "one\ntwo\nthree\nfuor\nfive\nsix\nseven\neight\nnine\n";
+        let corrected = original.replace("fuor", "four");
+        assert_eq!(
+            unified_diff(Path::new("docs/a.txt"), original, &corrected),
+            "--- a/docs/a.txt\n+++ b/docs/a.txt\n@@ -1,7 +1,7 @@\n one\n two\n three\n\
+             -fuor\n+four\n five\n six\n seven\n"
+        );
+
+        // The missing newline at the end of the file is kept
+        assert_eq!(
+            unified_diff(Path::new("/tmp/b.txt"), "teh end", "the end"),
+            "--- /tmp/b.txt\n+++ /tmp/b.txt\n@@ -1 +1 @@\n-teh end\n\\ No newline at end of file\n\
+             +the end\n\\ No newline at end of file\n"
+        );
+    }
+
+    #[test]
+    fn test_recording() {
+        let mut patch = Patch::new(PatchTarget::Stdout);
+        patch.record(Path::new("/b.txt"), "sme\n", "some\n");
+        patch.record(Path::new("/a.txt"), "txet\n", "test\n");
+        patch.record(Path::new("/a.txt"), "txet\n", "text\n");
+
+        let mut out = Vec::new();
+        patch.write(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "--- /a.txt\n+++ /a.txt\n@@ -1 +1 @@\n-txet\n+text\n\
+             --- /b.txt\n+++ /b.txt\n@@ -1 +1 @@\n-sme\n+some\n"
+        );
+
+        // Saving the original text again leaves the file out
+        patch.record(Path::new("/a.txt"), "txet\n", "txet\n");
+        patch.record(Path::new("/b.txt"), "sme\n", "sme\n");
+        assert!(patch.is_empty());
+    }
+}
@@ -0,0 +1,533 @@
+//! Locating the program files (the dictionaries and the files the program writes itself).
+//!
+//! Dictionaries are searched for in the following directories, in order:
+//! 1. the directory passed with `--data-dir`,
+//! 2. `$SPELCHEK_HOME`,
+//! 3. `$XDG_DATA_HOME/spelchek` (`~/.local/share/spelchek` if `XDG_DATA_HOME` isn't set),
+//! 4. `~/.spelchek`, where `make dict` installs them,
+//! 5. the system-wide `/usr/local/share/spelchek` and `/usr/share/spelchek`.
+//!
+//! The word list can also be passed directly with `--dict`, in which case it isn't searched for.
+//! The same goes for the style guide's `forbidden.txt` and `replacements.txt`, the multi-word
+//! `phrases.txt` and the `corrections.txt` corpus, which can be passed with `--forbidden`,
+//! `--replacements`, `--phrases` and `--corrections`.
+//!
+//! Apart from the default dictionary stored directly in a data directory, dictionaries for other
+//! languages can be installed into its `dictionaries/<name>` subdirectory, and selected with
+//! `--lang <name>`.
+
+use std::ffi::OsString;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+
+use crate::prelude::*;
+use crate::spellchecker::embedded;
+
+pub const DICT_FILE_NAME: &str = "dict.txt";
+pub const SUGGESTION_DICT_FILE_NAME: &str = "suggestion_dict.txt";
+pub const COMPILED_DICT_FILE_NAME: &str = "dict.bin";
+pub const LANGUAGE_FILE_NAME: &str = "language";
+pub const FORBIDDEN_FILE_NAME: &str = "forbidden.txt";
+pub const REPLACEMENTS_FILE_NAME: &str = "replacements.txt";
+pub const PHRASES_FILE_NAME: &str = "phrases.txt";
+/// Misspellings and their corrections, which the model of typing errors is trained on
+pub const CORRECTIONS_FILE_NAME: &str = "corrections.txt";
+/// The user's personal word list, in the user directory
+pub const USER_WORDS_FILE_NAME: &str = "words.txt";
+/// The corrections the user has accepted, in the user directory
+pub const HISTORY_FILE_NAME: &str = "history.tsv";
+/// The word list shared by a project, found by walking up from the checked file
+pub const PROJECT_WORDS_FILE_NAME: &str = ".spelchek-words.txt";
+
+/// The name of the dictionary stored directly in a data directory
+pub const DEFAULT_DICTIONARY: &str = "default";
+const DICTIONARIES_DIR_NAME: &str = "dictionaries";
+
+const SYSTEM_DATA_DIRS: [&str; 2] = ["/usr/local/share/spelchek", "/usr/share/spelchek"];
+
+/// The files the dictionary is loaded from. A list which is `None` wasn't found on disk, and the
+/// copy embedded in the binary should be used in its place.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DictionaryPaths {
+    pub dict: Option<PathBuf>,
+    pub suggestion_dict: Option<PathBuf>,
+    pub compiled: Option<PathBuf>, // Where the compiled dictionary is cached
+    pub forbidden: Option<PathBuf>, // The style guide's forbidden words, if there are any
+    pub replacements: Option<PathBuf>, // The style guide's preferred terms, if there are any
+    pub phrases: Option<PathBuf>,  // The multi-word phrases, if there are any
+    pub corrections: Option<PathBuf>, // The corpus of corrections, or `None` for the embedded one
+}
+
+/// The locations of the program files requested by the user, used to find everything else.
+#[derive(Debug, Default, Clone)]
+pub struct Locations {
+    pub dict: Option<PathBuf>,         // The word list passed with `--dict`
+    pub data_dir: Option<PathBuf>,     // The directory passed with `--data-dir`
+    pub dictionary: Option<String>,    // The name of the dictionary passed with `--lang`
+    pub forbidden: Option<PathBuf>,    // The forbidden words list passed with `--forbidden`
+    pub replacements: Option<PathBuf>, // The replacements list passed with `--replacements`
+    pub phrases: Option<PathBuf>,      // The phrase list passed with `--phrases`
+    pub corrections: Option<PathBuf>,  // The corrections corpus passed with `--corrections`
+}
+
+/// Returns the directory of the dictionary called `name` inside of a data directory
+pub fn dictionary_dir(data_dir: &Path, name: &str) -> PathBuf {
+    if name == DEFAULT_DICTIONARY {
+        data_dir.to_path_buf()
+    } else {
+        data_dir.join(DICTIONARIES_DIR_NAME).join(name)
+    }
+}
+
+/// Returns the names of the dictionaries installed in a data directory
+pub fn installed_dictionaries(data_dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+
+    if data_dir.join(DICT_FILE_NAME).is_file() {
+        names.push(DEFAULT_DICTIONARY.to_string());
+    }
+
+    let mut other_names: Vec<String> = std::fs::read_dir(data_dir.join(DICTIONARIES_DIR_NAME))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().join(DICT_FILE_NAME).is_file())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    other_names.sort();
+
+    names.append(&mut other_names);
+    names
+}
+
+/// Returns the first file called `file_name` in `start` or any of its ancestors
+pub fn find_upwards(start: &Path, file_name: &str) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(file_name))
+        .find(|path| path.is_file())
+}
+
+/// Returns the project word list for the checked file: the one found by walking up from it, or,
+/// if there is none yet, where it should be created - at the root of the repository the file is
+/// in, or next to the file if it isn't in one.
+pub fn project_words_path(checked_file: &Path) -> PathBuf {
+    let start = checked_file.parent().unwrap_or(checked_file);
+    if let Some(path) = find_upwards(start, PROJECT_WORDS_FILE_NAME) {
+        return path;
+    }
+
+    let root = start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(start);
+    root.join(PROJECT_WORDS_FILE_NAME)
+}
+
+impl Locations {
+    /// Returns the directories searched for dictionaries, in order, each with a short description
+    /// of where it comes from.
+    pub fn data_dirs(&self) -> Vec<(PathBuf, &'static str)> {
+        self.data_dirs_with(&|name| std::env::var_os(name), home::home_dir())
+    }
+
+    /// Returns the directory the program writes its own files to (like the compiled dictionary):
+    /// the one passed with `--data-dir`, `$SPELCHEK_HOME`, `~/.spelchek` if it exists, or the XDG
+    /// data directory.
+    pub fn user_dir(&self) -> Result<PathBuf> {
+        self.user_dir_with(&|name| std::env::var_os(name), home::home_dir())
+    }
+
+    /// The name of the selected dictionary
+    pub fn dictionary_name(&self) -> &str {
+        self.dictionary.as_deref().unwrap_or(DEFAULT_DICTIONARY)
+    }
+
+    /// Returns the locations of the dictionary called `name` in the same data directories, without
+    /// the files passed for the selected dictionary
+    pub fn of_dictionary(&self, name: &str) -> Locations {
+        Locations {
+            data_dir: self.data_dir.clone(),
+            dictionary: Some(name.to_string()),
+            ..Locations::default()
+        }
+    }
+
+    /// Finds the word lists to load. Fails, listing every location that was tried, if the word
+    /// list or the suggestion list isn't on disk and isn't embedded in the binary either. Only the
+    /// default dictionary can use the embedded lists.
+    pub fn resolve_dictionary(&self) -> Result<DictionaryPaths> {
+        self.resolve_dictionary_with(&|name| std::env::var_os(name), home::home_dir(), true)
+    }
+
+    /// Finds the files of the selected dictionary like [`Locations::resolve_dictionary`], but
+    /// without requiring a suggestion list, so that incomplete dictionaries can be inspected.
+    pub fn find_dictionary(&self) -> Result<DictionaryPaths> {
+        self.resolve_dictionary_with(&|name| std::env::var_os(name), home::home_dir(), false)
+    }
+
+    fn resolve_dictionary_with(
+        &self,
+        env: &dyn Fn(&str) -> Option<OsString>,
+        home: Option<PathBuf>,
+        require_suggestion_list: bool,
+    ) -> Result<DictionaryPaths> {
+        let name = self.dictionary_name();
+        let is_default = name == DEFAULT_DICTIONARY;
+
+        let data_dirs = self.data_dirs_with(env, home.clone());
+        let find = |file_name: &str| {
+            data_dirs
+                .iter()
+                .map(|(dir, _)| dictionary_dir(dir, name).join(file_name))
+                .find(|path| path.is_file())
+        };
+
+        let dict = match &self.dict {
+            Some(path) if path.is_file() => Some(path.clone()),
+            Some(path) => {
+                return Err(anyhow!(
+                    "the dictionary passed with --dict doesn't exist: {}",
+                    path.display()
+                ));
+            }
+            None => find(DICT_FILE_NAME),
+        };
+
+        // Lists every directory the file was looked for in
+        let not_found = |mut message: String, hint: &str| {
+            for (dir, source) in &data_dirs {
+                let dir = dictionary_dir(dir, name);
+                let _ = write!(message, "\n    {} ({source})", dir.display());
+            }
+            message.push_str(hint);
+            anyhow!(message)
+        };
+
+        // Only the default dictionary is embedded
+        if dict.is_none() && !(is_default && embedded::is_available()) {
+            let message = match is_default {
+                true => format!("couldn't find a dictionary, looked for {DICT_FILE_NAME} in:"),
+                false => format!("couldn't find the '{name}' dictionary, looked in:"),
+            };
+            return Err(not_found(
+                message,
+                "\ninstall one with `make dict`, or pass one with --dict",
+            ));
+        }
+
+        // The embedded suggestion list is English, so other dictionaries need their own
+        let suggestion_dict = find(SUGGESTION_DICT_FILE_NAME);
+        if require_suggestion_list
+            && suggestion_dict.is_none()
+            && !(is_default && embedded::is_available())
+        {
+            let (message, hint) = match is_default {
+                true => (
+                    "couldn't find the suggestion list".to_string(),
+                    "install one with `make dict`".to_string(),
+                ),
+                false => (
+                    format!("couldn't find the suggestion list of the '{name}' dictionary"),
+                    format!("add one with the popularity of its words to the '{name}' dictionary"),
+                ),
+            };
+            return Err(not_found(
+                format!("{message}, looked for {SUGGESTION_DICT_FILE_NAME} in:"),
+                &format!("\n{hint}"),
+            ));
+        }
+
+        Ok(DictionaryPaths {
+            dict,
+            suggestion_dict,
+            compiled: self
+                .user_dir_with(env, home)
+                .ok()
+                .map(|dir| dictionary_dir(&dir, name).join(COMPILED_DICT_FILE_NAME)),
+            forbidden: self.forbidden.clone().or_else(|| find(FORBIDDEN_FILE_NAME)),
+            replacements: self
+                .replacements
+                .clone()
+                .or_else(|| find(REPLACEMENTS_FILE_NAME)),
+            phrases: self.phrases.clone().or_else(|| find(PHRASES_FILE_NAME)),
+            corrections: self
+                .corrections
+                .clone()
+                .or_else(|| find(CORRECTIONS_FILE_NAME)),
+        })
+    }
+
+    fn data_dirs_with(
+        &self,
+        env: &dyn Fn(&str) -> Option<OsString>,
+        home: Option<PathBuf>,
+    ) -> Vec<(PathBuf, &'static str)> {
+        let mut dirs: Vec<(PathBuf, &'static str)> = Vec::new();
+
+        if let Some(dir) = &self.data_dir {
+            dirs.push((dir.clone(), "--data-dir"));
+        }
+        if let Some(dir) = non_empty_var(env, "SPELCHEK_HOME") {
+            dirs.push((dir, "SPELCHEK_HOME"));
+        }
+        if let Some(dir) = xdg_data_dir(env, home.as_deref()) {
+            dirs.push((dir, "XDG data directory"));
+        }
+        if let Some(home) = &home {
+            dirs.push((home.join(".spelchek"), "home directory"));
+        }
+        for dir in SYSTEM_DATA_DIRS {
+            dirs.push((PathBuf::from(dir), "system-wide"));
+        }
+
+        dirs
+    }
+
+    fn user_dir_with(
+        &self,
+        env: &dyn Fn(&str) -> Option<OsString>,
+        home: Option<PathBuf>,
+    ) -> Result<PathBuf> {
+        if let Some(dir) = &self.data_dir {
+            return Ok(dir.clone());
+        }
+        if let Some(dir) = non_empty_var(env, "SPELCHEK_HOME") {
+            return Ok(dir);
+        }
+
+        // Keep using the old location for existing installations
+        if let Some(legacy_dir) = home.as_ref().map(|home| home.join(".spelchek")) {
+            if legacy_dir.is_dir() {
+                return Ok(legacy_dir);
+            }
+        }
+
+        xdg_data_dir(env, home.as_deref()).ok_or_else(|| {
+            anyhow!("couldn't find the home directory, set SPELCHEK_HOME or pass --data-dir")
+        })
+    }
+}
+
+fn non_empty_var(env: &dyn Fn(&str) -> Option<OsString>, name: &str) -> Option<PathBuf> {
+    env(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+fn xdg_data_dir(env: &dyn Fn(&str) -> Option<OsString>, home: Option<&Path>) -> Option<PathBuf> {
+    // Relative paths are invalid according to the specification and should be ignored
+    match non_empty_var(env, "XDG_DATA_HOME").filter(|dir| dir.is_absolute()) {
+        Some(dir) => Some(dir.join("spelchek")),
+        None => home.map(|home| home.join(".local/share/spelchek")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_dir;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<OsString> {
+        let vars: Vec<(String, OsString)> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), OsString::from(value)))
+            .collect();
+        move |name| {
+            vars.iter()
+                .find(|(var, _)| var == name)
+                .map(|(_, value)| value.clone())
+        }
+    }
+
+    fn dirs(locations: &Locations, vars: &[(&str, &str)], home: Option<&str>) -> Vec<PathBuf> {
+        locations
+            .data_dirs_with(&env(vars), home.map(PathBuf::from))
+            .into_iter()
+            .map(|(dir, _)| dir)
+            .collect()
+    }
+
+    #[test]
+    fn test_default_data_dirs() {
+        assert_eq!(
+            dirs(&Locations::default(), &[], Some("/home/user")),
+            vec![
+                PathBuf::from("/home/user/.local/share/spelchek"),
+                PathBuf::from("/home/user/.spelchek"),
+                PathBuf::from("/usr/local/share/spelchek"),
+                PathBuf::from("/usr/share/spelchek"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_data_dirs_order() {
+        let locations = Locations {
+            data_dir: Some(PathBuf::from("/flag")),
+            ..Default::default()
+        };
+        let vars = [
+            ("SPELCHEK_HOME", "/spelchek_home"),
+            ("XDG_DATA_HOME", "/xdg"),
+        ];
+        assert_eq!(
+            dirs(&locations, &vars, Some("/home/user"))[..4],
+            [
+                PathBuf::from("/flag"),
+                PathBuf::from("/spelchek_home"),
+                PathBuf::from("/xdg/spelchek"),
+                PathBuf::from("/home/user/.spelchek"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_data_dirs_without_home() {
+        assert_eq!(
+            dirs(
+                &Locations::default(),
+                &[("XDG_DATA_HOME", "relative")],
+                None
+            ),
+            vec![
+                PathBuf::from("/usr/local/share/spelchek"),
+                PathBuf::from("/usr/share/spelchek"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_user_dir() {
+        let locations = Locations::default();
+        assert_eq!(
+            locations
+                .user_dir_with(&env(&[("SPELCHEK_HOME", "/sh")]), None)
+                .unwrap(),
+            PathBuf::from("/sh")
+        );
+        assert_eq!(
+            locations
+                .user_dir_with(&env(&[("SPELCHEK_HOME", "")]), Some("/nonexistent".into()))
+                .unwrap(),
+            PathBuf::from("/nonexistent/.local/share/spelchek")
+        );
+        assert!(locations.user_dir_with(&env(&[]), None).is_err());
+    }
+
+    #[test]
+    fn test_missing_explicit_dict() {
+        let locations = Locations {
+            dict: Some(PathBuf::from("/nonexistent/words.txt")),
+            ..Default::default()
+        };
+        assert!(locations.resolve_dictionary().is_err());
+    }
+
+    #[test]
+    fn test_missing_named_dictionary() {
+        let locations = Locations {
+            data_dir: Some(PathBuf::from("/nonexistent")),
+            dictionary: Some("xx_nonexistent".to_string()),
+            ..Default::default()
+        };
+        let error = locations.resolve_dictionary().unwrap_err().to_string();
+        assert!(error.contains("/nonexistent/dictionaries/xx_nonexistent (--data-dir)"));
+    }
+
+    #[test]
+    fn test_missing_suggestion_list() {
+        let dir = test_dir("missing-suggestions");
+        std::fs::write(dir.join(DICT_FILE_NAME), "apple\n").unwrap();
+        let locations = Locations {
+            data_dir: Some(dir.clone()),
+            ..Default::default()
+        };
+
+        // The embedded suggestion list is used in its place
+        match locations.resolve_dictionary_with(&env(&[]), None, true) {
+            Ok(paths) => {
+                assert!(embedded::is_available());
+                assert_eq!(paths.dict, Some(dir.join(DICT_FILE_NAME)));
+                assert_eq!(paths.suggestion_dict, None);
+            }
+            Err(error) => {
+                assert!(!embedded::is_available());
+                let error = error.to_string();
+                assert!(error.contains(SUGGESTION_DICT_FILE_NAME));
+                assert!(error.contains(&format!("{} (--data-dir)", dir.display())));
+            }
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_missing_named_suggestion_list() {
+        let dir = test_dir("missing-named-suggestions");
+        let dictionary = dictionary_dir(&dir, "pl");
+        std::fs::create_dir_all(&dictionary).unwrap();
+        std::fs::write(dictionary.join(DICT_FILE_NAME), "jabłko\n").unwrap();
+        let locations = Locations {
+            data_dir: Some(dir.clone()),
+            dictionary: Some("pl".to_string()),
+            ..Default::default()
+        };
+
+        // The embedded English suggestion list isn't used for other languages
+        let error = locations
+            .resolve_dictionary_with(&env(&[]), None, true)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("suggestion list of the 'pl' dictionary"));
+        assert!(error.contains(&format!("{} (--data-dir)", dictionary.display())));
+
+        std::fs::write(dictionary.join(SUGGESTION_DICT_FILE_NAME), "jabłko\t10\n").unwrap();
+        let paths = locations
+            .resolve_dictionary_with(&env(&[]), None, true)
+            .unwrap();
+        assert_eq!(
+            paths.suggestion_dict,
+            Some(dictionary.join(SUGGESTION_DICT_FILE_NAME))
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_project_words_path() {
+        let dir = test_dir("project");
+        std::fs::create_dir_all(dir.join("repo/.git")).unwrap();
+        std::fs::create_dir_all(dir.join("repo/docs/guide")).unwrap();
+        let checked_file = dir.join("repo/docs/guide/intro.md");
+
+        // Created at the root of the repository if there is none yet
+        assert_eq!(
+            project_words_path(&checked_file),
+            dir.join("repo").join(PROJECT_WORDS_FILE_NAME)
+        );
+
+        // The closest existing one is used
+        std::fs::write(dir.join("repo/docs").join(PROJECT_WORDS_FILE_NAME), "").unwrap();
+        assert_eq!(
+            project_words_path(&checked_file),
+            dir.join("repo/docs").join(PROJECT_WORDS_FILE_NAME)
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_dictionary_dir() {
+        assert_eq!(
+            dictionary_dir(Path::new("/data"), DEFAULT_DICTIONARY),
+            PathBuf::from("/data")
+        );
+        assert_eq!(
+            dictionary_dir(Path::new("/data"), "pl"),
+            PathBuf::from("/data/dictionaries/pl")
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! The `-a` pipe mode, speaking the protocol of `ispell -a`, so that editors and other tools
+//! which use ispell (like Emacs' ispell and flyspell) can use spelchek as their spellchecker.
+//!
+//! ```text
+//! spelchek -a
+//! ```
+//!
+//! After printing a version banner, every line read from stdin is answered on stdout. A line of
+//! text is answered with one line for each of its words, followed by an empty line:
+//! - `*` for a correct word (left out in terse mode),
+//! - `& <word> <count> <offset>: <suggestion>, <suggestion>, ...` for a misspelling,
+//! - `# <word> <offset>` for a misspelling without suggestions,
+//!
+//! where the offset is the position of the word in the line, counted in characters. Lines
+//! starting with one of these characters are commands instead:
+//! - `^` checks the rest of the line (used by clients to send text starting with a command
+//!   character),
+//! - `*word` adds the word to the user's word list, and `&word` adds it in lowercase,
+//! - `@word` accepts the word until the program exits,
+//! - `#` saves the user's word list (a no-op, as the words are saved as soon as they are added),
+//! - `!` enters and `%` leaves terse mode.
+//!
+//! The other commands of the protocol (`+`, `-`, `~` and `$$`) are accepted and ignored, and so
+//! are ispell's command line options.
+//!
+//! `spelchek -v` (or `-vv`) only prints the banner and exits, which is how clients like Emacs'
+//! ispell.el find out the version before starting the pipe.
+
+use std::env;
+use std::io::{self, BufRead, Write};
+
+use crate::paths::Locations;
+use crate::prelude::*;
+use crate::spellchecker::layers::LayerKind;
+use crate::spellchecker::tokenizer::{PlainText, Tokenizer};
+use crate::spellchecker::{Spellchecker, SuggestionLimits};
+
+/// The first line printed, telling the clients which version of the protocol is spoken
+const BANNER: &str = concat!(
+    "@(#) International Ispell Version 3.1.20 (but really spelchek ",
+    env!("CARGO_PKG_VERSION"),
+    ")"
+);
+
+/// The options of ispell followed by a value, which are skipped together with it
+const OPTIONS_WITH_VALUE: [&str; 5] = ["-d", "-p", "-w", "-T", "-W"];
+
+#[derive(Debug, PartialEq)]
+pub enum PipeCommand {
+    Serve,   // `-a`
+    Version, // `-v` or `-vv`
+}
+
+impl PipeCommand {
+    /// Parses the arguments following `-a`. The options of ispell and aspell that clients pass
+    /// are ignored.
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                option if OPTIONS_WITH_VALUE.contains(&option) => {
+                    args.next();
+                }
+                option if option.starts_with('-') => {}
+                _ => return Err(anyhow::anyhow!("unexpected argument '{arg}'")),
+            }
+        }
+        Ok(PipeCommand::Serve)
+    }
+
+    pub fn run(&self, locations: &Locations, limits: SuggestionLimits) -> Result<()> {
+        if *self == PipeCommand::Version {
+            println!("{BANNER}");
+            return Ok(());
+        }
+
+        let mut spellchecker = Spellchecker::load(locations)?;
+        spellchecker.set_suggestion_limits(limits);
+        spellchecker.load_project_words(&env::current_dir()?.join(crate::STDIN_NAME))?;
+        serve(&mut spellchecker, io::stdin().lock(), io::stdout().lock())
+    }
+}
+
+/// Answers the lines of `input` until it ends.
+pub fn serve(
+    spellchecker: &mut Spellchecker,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> Result<()> {
+    writeln!(output, "{BANNER}")?;
+    output.flush()?;
+
+    let mut terse = false;
+    for line in input.lines() {
+        let line = line?;
+        let added = match line.chars().next() {
+            Some('*') => Some((LayerKind::User, line[1..].to_string())),
+            Some('&') => Some((LayerKind::User, line[1..].to_lowercase())),
+            Some('@') => Some((LayerKind::Session, line[1..].to_string())),
+            Some('!') => {
+                terse = true;
+                None
+            }
+            Some('%') => {
+                terse = false;
+                None
+            }
+            Some('#' | '+' | '-' | '~' | '$') => None,
+            Some('^') => {
+                check_line(spellchecker, &line[1..], 1, terse, &mut output)?;
+                None
+            }
+            _ => {
+                check_line(spellchecker, &line, 0, terse, &mut output)?;
+                None
+            }
+        };
+
+        // The protocol has no way of reporting errors, so they go to stderr
+        if let Some((layer, word)) = added {
+            if let Err(error) = spellchecker.add_word(layer, word.trim()) {
+                eprintln!("{error:#}");
+            }
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// Answers a line of text, whose first character is at `offset` in the line read
+fn check_line(
+    spellchecker: &Spellchecker,
+    text: &str,
+    offset: usize,
+    terse: bool,
+    output: &mut impl Write,
+) -> Result<()> {
+    let mut misspellings = spellchecker
+        .find_misspellings(text, &PlainText)
+        .into_iter()
+        .peekable();
+
+    // A misspelled phrase covers several words, which are answered together
+    let mut checked_until = 0;
+    for word in PlainText.tokenize(text) {
+        if word.start < checked_until {
+            continue;
+        }
+
+        let mut misspelled = false;
+        while let Some(mut misspelling) =
+            misspellings.next_if(|misspelling| misspelling.get_start() < word.end)
+        {
+            misspelled = true;
+            checked_until = misspelling.get_end() + 1;
+            spellchecker.suggest_misspelling(&mut misspelling);
+
+            let word = misspelling.get_word();
+            let offset = offset + text[..misspelling.get_start()].chars().count();
+            let suggestions = misspelling.get_suggestions();
+            match suggestions.is_empty() {
+                true => writeln!(output, "# {word} {offset}")?,
+                false => writeln!(
+                    output,
+                    "& {word} {} {offset}: {}",
+                    suggestions.len(),
+                    suggestions.join(", ")
+                )?,
+            }
+        }
+
+        if !misspelled && !terse {
+            writeln!(output, "*")?;
+        }
+    }
+    writeln!(output)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spellchecker::style::StyleRules;
+    use crate::test_support::test_dictionary;
+
+    fn serve_to_string(input: &str) -> String {
+        let dict = test_dictionary();
+        let mut spellchecker = Spellchecker::with_dictionary(dict);
+        spellchecker.set_style_rules(StyleRules::from_sources("text\n", "", "").unwrap());
+        let mut output: Vec<u8> = Vec::new();
+        serve(&mut spellchecker, input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_checking_lines() {
+        let output = serve_to_string("this is sme text\n^żółw tihs\n");
+        let mut lines = output.lines();
+        assert_eq!(lines.next(), Some(BANNER));
+        assert_eq!(
+            lines.collect::<Vec<_>>(),
+            [
+                "*",
+                "*",
+                "& sme 3 8: some, is, this",
+                "& text 3 12: this, is, some",
+                "",
+                "& żółw 3 1: this, some, is",
+                "& tihs 3 6: this, is, some",
+                ""
+            ]
+        );
+    }
+
+    #[test]
+    fn test_commands() {
+        let output = serve_to_string("!\nthis sme\n@sme\nsme\n%\nsme\n");
+        assert_eq!(
+            output.lines().skip(1).collect::<Vec<_>>(),
+            ["& sme 3 5: some, is, this", "", "", "*", ""]
+        );
+    }
+
+    #[test]
+    fn test_parsing() {
+        let args = |args: &[&str]| -> Vec<String> { args.iter().map(|s| s.to_string()).collect() };
+        assert_eq!(
+            PipeCommand::parse(&args(&["-m", "-d", "english", "--encoding=utf-8"])).unwrap(),
+            PipeCommand::Serve
+        );
+        assert!(PipeCommand::parse(&args(&["file.txt"])).is_err());
+
+        // Only the version is printed for `-v` and `-vv`, without loading anything
+        for flag in ["-v", "-vv"] {
+            let config = crate::Config::build(&args(&[flag])).unwrap();
+            assert_eq!(
+                config.get_command(),
+                &crate::Command::Pipe(PipeCommand::Version)
+            );
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use std::rc::Rc;
 
 use crate::app_state::{AppState, Screen};
-use crate::spellchecker::Misspelling;
+use crate::spellchecker::layers::LayerKind;
+use crate::spellchecker::{Misspelling, MisspellingKind};
 
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::Style;
@@ -11,17 +12,29 @@ use ratatui::widgets::block::Title;
 use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph};
 use ratatui::Frame;
 
-const KEYMAP_BAR_TEXT: &str =
-    "Tab/S-Tab: next/prev misspelling   j/k: next/prev suggestion   q: quit   s: save";
+const KEYMAP_BAR_TEXT: &str = "Tab/S-Tab: next/prev misspelling   j/k: next/prev suggestion   \
+     i/a/p: add to session/user/project dictionary   ]/[: next/prev file   q: quit   s: save";
 
 pub fn render(frame: &mut Frame, app: &mut AppState) {
     let layout_fields: Rc<[Rect]> = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(100), Constraint::Min(1)])
+        .constraints([
+            Constraint::Percentage(100),
+            Constraint::Min(1),
+            Constraint::Min(1),
+        ])
         .split(frame.size());
-    let bottom_bar: Rect = layout_fields[1]; // The Rect for the bottom bar, containing a list of the
+    let status_bar: Rect = layout_fields[1]; // The Rect for the status bar, describing the
+                                             // selected misspelling or the outcome of the last action
+    let bottom_bar: Rect = layout_fields[2]; // The Rect for the bottom bar, containing a list of the
                                              // programs keymaps
 
+    let status = app
+        .status
+        .clone()
+        .or_else(|| app.describe_selected_misspelling())
+        .unwrap_or_default();
+    frame.render_widget(Paragraph::new(status), status_bar);
     frame.render_widget(
         Paragraph::new(KEYMAP_BAR_TEXT).style(Style::default().fg(Color::Blue)),
         bottom_bar,
@@ -35,7 +48,7 @@ pub fn render(frame: &mut Frame, app: &mut AppState) {
             app.spellchecker.misspellings(),
             app.selected_misspelling,
         )
-        .block(Block::new().title("Text").borders(Borders::ALL)),
+        .block(Block::new().title(text_title(app)).borders(Borders::ALL)),
         layout_fields[1],
     );
 
@@ -48,7 +61,17 @@ pub fn render(frame: &mut Frame, app: &mut AppState) {
         &mut app.misspellings_list_state,
     );
 
-    let suggestions = app.get_suggestions().unwrap_or(&Vec::new()).clone();
+    // Suggestions coming from the word lists stacked on top of the base dictionary are labelled
+    // with the layer they come from
+    let suggestions: Vec<String> = app
+        .get_suggestions()
+        .unwrap_or(&Vec::new())
+        .iter()
+        .map(|suggestion| match app.layer_accepting(suggestion) {
+            Some(layer) if layer != LayerKind::Base => format!("{suggestion} ({layer})"),
+            _ => suggestion.clone(),
+        })
+        .collect();
 
     let mut state = ListState::default();
     state.select(app.selected_suggestion);
@@ -72,6 +95,21 @@ pub fn render(frame: &mut Frame, app: &mut AppState) {
     }
 }
 
+/// The title of the checked text: the file's path, and when many files are checked, its position
+/// among them and the number of its misspellings
+fn text_title(app: &AppState) -> String {
+    if app.file_count() <= 1 {
+        return String::from("Text");
+    }
+    format!(
+        "{} ({}/{}, {} misspellings)",
+        app.get_file_path().display(),
+        app.current_file() + 1,
+        app.file_count(),
+        app.misspelling_counts()[app.current_file()]
+    )
+}
+
 /// Determines whether a position is the start of some Misspelling. If it is, returns the index of
 /// that misspelling in the passed Misspelling slice (&[Misspelling])
 fn starts_misspelling(idx: usize, misspellings: &[Misspelling]) -> Option<usize> {
@@ -82,7 +120,7 @@ fn starts_misspelling(idx: usize, misspellings: &[Misspelling]) -> Option<usize>
     let mut left = 0;
     let mut right = misspellings.len() - 1;
     while left != right {
-        let mid = (left + right + 1) / 2;
+        let mid = (left + right).div_ceil(2);
         let misspelling_start_idx = misspellings[mid].get_start();
 
         if misspelling_start_idx > idx {
@@ -99,14 +137,26 @@ fn starts_misspelling(idx: usize, misspellings: &[Misspelling]) -> Option<usize>
     }
 }
 
-/// Creates a span representing a Misspelling. If `highlight` is `true`, the Misspelling is also
-/// highlighted (has a background).
-fn create_misspelling_span(text: &str, highlight: bool) -> Span {
-    let style: Style = match highlight {
-        false => Style::new()
-            .underline_color(Color::LightRed)
+/// Creates a span representing a Misspelling. The color of the underline depends on the kind of
+/// the misspelling, so that wrong casing and words banned by the style guide stand out from
+/// ordinary misspellings. If `highlight` is `true`, the Misspelling is also highlighted (has a
+/// background).
+fn create_misspelling_span(text: &str, kind: MisspellingKind, highlight: bool) -> Span<'_> {
+    let underline_color = match kind {
+        MisspellingKind::Spelling => Color::LightRed,
+        MisspellingKind::Capitalization => Color::LightYellow,
+        MisspellingKind::Forbidden | MisspellingKind::Replacement => Color::LightMagenta,
+    };
+
+    let style: Style = match (highlight, kind.is_style_issue()) {
+        (false, false) => Style::new()
+            .underline_color(underline_color)
             .add_modifier(Modifier::UNDERLINED),
-        true => Style::new().bg(Color::Blue),
+        (false, true) => Style::new()
+            .underline_color(underline_color)
+            .add_modifier(Modifier::UNDERLINED | Modifier::ITALIC),
+        (true, false) => Style::new().bg(Color::Blue),
+        (true, true) => Style::new().bg(Color::Magenta),
     };
 
     Span::styled(text, style)
@@ -124,7 +174,7 @@ fn create_spellchecked_text<'a>(
     let mut current_line_spans: Vec<Span> = Vec::new(); // The spans on the currently processed line
     let mut span_start: usize = 0; // The index of the character on which the span to be added next
                                    // starts, or the one after the previously added span
-    for (i, c) in buf.chars().enumerate() {
+    for (i, c) in buf.char_indices() {
         // If the current index is inside some misspelling, add it as a span.
         if let Some(misspelling_idx) = starts_misspelling(i, misspellings) {
             let misspelling: &Misspelling = &misspellings[misspelling_idx];
@@ -133,6 +183,7 @@ fn create_spellchecked_text<'a>(
             // Add misspelling span to the current line spans vector
             current_line_spans.push(create_misspelling_span(
                 &buf[misspelling.get_start()..=misspelling.get_end()],
+                misspelling.get_kind(),
                 Some(misspelling_idx) == highlight_misspelling_index,
             ));
 
@@ -278,7 +329,7 @@ mod tests {
         );
     }
 
-    fn miss_span(s: &str) -> Span {
+    fn miss_span(s: &str) -> Span<'_> {
         Span::styled(
             s,
             Style::new()
@@ -334,10 +385,32 @@ mod tests {
         );
     }
 
+    #[test]
+    // The positions of the Misspellings are byte offsets, which differ from the character indices
+    // after non-ASCII characters
+    fn test_creating_text_paragraph_multibyte() {
+        let text = "Zażółć gęślą jaźń\nmispeled";
+        let misspellings = vec![
+            Misspelling::new(String::from("jaźń"), 20, 25),
+            Misspelling::new(String::from("mispeled"), 27, 34),
+        ];
+        assert_eq!(
+            create_spellchecked_text(text, &misspellings, None),
+            Paragraph::new(vec![
+                Line::from(vec![
+                    Span::raw("Zażółć gęślą "),
+                    miss_span("jaźń"),
+                    Span::raw("\n")
+                ]),
+                Line::from(vec![Span::raw(""), miss_span("mispeled")])
+            ])
+        );
+    }
+
     #[test]
     fn test_create_misspelling() {
         assert_eq!(
-            create_misspelling_span("hello world", false),
+            create_misspelling_span("hello world", MisspellingKind::Spelling, false),
             Span::styled(
                 "hello world",
                 Style::new()
@@ -347,8 +420,22 @@ mod tests {
         );
 
         assert_eq!(
-            create_misspelling_span("hello world", true),
+            create_misspelling_span("hello world", MisspellingKind::Spelling, true),
             Span::styled("hello world", Style::new().bg(Color::Blue))
         );
+
+        assert_eq!(
+            create_misspelling_span("e-mail", MisspellingKind::Replacement, false),
+            Span::styled(
+                "e-mail",
+                Style::new()
+                    .underline_color(Color::LightMagenta)
+                    .add_modifier(Modifier::UNDERLINED | Modifier::ITALIC)
+            )
+        );
+        assert_eq!(
+            create_misspelling_span("e-mail", MisspellingKind::Forbidden, true),
+            Span::styled("e-mail", Style::new().bg(Color::Magenta))
+        );
     }
 }
@@ -0,0 +1,669 @@
+//! Reporting the misspellings of a file without the TUI, for scripts and CI.
+//!
+//! By default every misspelling is printed on a line of its own in the format compilers use,
+//! which editors can jump to:
+//! ```text
+//! README.md:3:14: teh (suggestions: the, tech, ten)
+//! ```
+//! Lines and columns start at 1, and columns count characters.
+//!
+//! For other tools the report can also be written as JSON (`--output json`), a single object:
+//! ```text
+//! {
+//!   "schema_version": 1,
+//!   "misspellings": [<misspelling>, ...],
+//!   "summary": {"files": 1, "misspellings": 1}
+//! }
+//! ```
+//! or as JSON Lines (`--output jsonl`), one `<misspelling>` object per line, each with
+//! `"type": "misspelling"`, followed by the summary object with `"type": "summary"`. Every line
+//! carries the `schema_version`. A misspelling looks like this:
+//! ```text
+//! {
+//!   "path": "README.md",
+//!   "word": "teh",
+//!   "kind": "spelling",           // or "capitalization", "forbidden", "replacement"
+//!   "byte_range": {"start": 40, "end": 43},    // end exclusive
+//!   "range": {"start": {"line": 3, "column": 14}, "end": {"line": 3, "column": 17}},
+//!   "suggestions": [{"word": "the", "distance": 1, "popularity": 23135851162, "score": 13.8}, ...]
+//! }
+//! ```
+//! The distance of a suggestion is the number of typos between it and the misspelling, counting a
+//! swap of two adjacent letters as one. The suggestions are ordered by their score, the log
+//! probability of the suggestion being the intended word, except that the corrections accepted
+//! before come first. The popularity of a suggestion is `null` if it isn't in the suggestion list
+//! (like the corrections of wrongly cased words). The schema version is only raised when fields are
+//! removed or change meaning, new fields can be added without it.
+//!
+//! Finally, `--output sarif` writes a SARIF 2.1.0 log for code scanning dashboards. Every kind of
+//! misspelling is a rule, and every misspelling a result with its region and fixes replacing it
+//! with the top suggestions.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use anyhow::anyhow;
+use serde::Serialize;
+
+use crate::app_state::correction_for;
+use crate::prelude::*;
+use crate::spellchecker::algorithm::transposition_distance;
+use crate::spellchecker::{Misspelling, MisspellingKind, Spellchecker};
+
+/// The version of the JSON report's schema
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The number of suggestions printed for a misspelling in the text format
+const PRINTED_SUGGESTIONS: usize = 5;
+/// The number of suggestions offered as fixes in the SARIF log
+const SARIF_FIXES: usize = 3;
+
+/// The kinds of misspellings, which are the rules of the SARIF log, with their descriptions
+const RULES: [(MisspellingKind, &str); 4] = [
+    (
+        MisspellingKind::Spelling,
+        "The word isn't in the dictionary",
+    ),
+    (
+        MisspellingKind::Capitalization,
+        "The word has to be written in a different casing",
+    ),
+    (
+        MisspellingKind::Forbidden,
+        "The word is forbidden by the style guide",
+    ),
+    (
+        MisspellingKind::Replacement,
+        "The style guide prefers a different term",
+    ),
+];
+
+/// How the report is written
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    /// `path:line:col: word (suggestions: ...)` lines
+    #[default]
+    Text,
+    /// A single JSON object
+    Json,
+    /// A JSON object per line
+    JsonLines,
+    /// A SARIF 2.1.0 log
+    Sarif,
+}
+
+impl OutputFormat {
+    /// The names the formats are selected by
+    pub const NAMES: [&'static str; 4] = ["text", "json", "jsonl", "sarif"];
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "jsonl" => Ok(OutputFormat::JsonLines),
+            "sarif" => Ok(OutputFormat::Sarif),
+            _ => Err(anyhow!(
+                "unknown output format '{name}' (available formats: {})",
+                OutputFormat::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Finds the line and column of byte offsets into a text.
+pub struct LineIndex<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>, // The byte offsets at which the lines start
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(text: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex { text, line_starts }
+    }
+
+    /// Returns the line and the column of the character at the byte offset, both starting at 1
+    pub fn position(&self, offset: usize) -> Position {
+        let line = self.line_of(offset);
+        let column = self.text[self.line_starts[line]..offset].chars().count();
+        Position {
+            line: line + 1,
+            column: column + 1,
+        }
+    }
+
+    /// Returns the line and the column of the byte offset, both starting at 0, with the column
+    /// counted in UTF-16 code units like the Language Server Protocol does
+    pub fn utf16_position(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_of(offset);
+        let column = self.text[self.line_starts[line]..offset]
+            .encode_utf16()
+            .count();
+        (line, column)
+    }
+
+    /// The index of the line containing the byte offset, starting at 0
+    fn line_of(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|&start| start <= offset) - 1
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone, Copy)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone, Copy)]
+pub struct Range<T> {
+    pub start: T,
+    pub end: T, // Exclusive
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct SuggestionRecord {
+    pub word: String,
+    pub distance: usize,         // The number of typos from the misspelled word
+    pub popularity: Option<i64>, // The popularity in the suggestion list
+    pub score: f64,              // The score the suggestion is ranked by
+}
+
+/// A misspelling as it is reported.
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct MisspellingRecord {
+    pub path: String,
+    pub word: String,
+    pub kind: MisspellingKind,
+    pub byte_range: Range<usize>,
+    pub range: Range<Position>,
+    pub suggestions: Vec<SuggestionRecord>,
+}
+
+/// When a check fails because of the misspellings it found.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Threshold {
+    pub max: usize, // The number of misspellings allowed, set with `--max-misspellings`
+    pub warn_only: bool, // Whether the misspellings are only reported, set with `--warn-only`
+}
+
+impl Threshold {
+    pub fn fails(&self, misspellings: usize) -> bool {
+        !self.warn_only && misspellings > self.max
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone, Copy, Default)]
+pub struct Summary {
+    pub files: usize,
+    pub misspellings: usize,
+}
+
+/// The name of the kind of a misspelling, as it is serialized in the report
+pub(crate) fn kind_name(kind: MisspellingKind) -> &'static str {
+    match kind {
+        MisspellingKind::Spelling => "spelling",
+        MisspellingKind::Capitalization => "capitalization",
+        MisspellingKind::Forbidden => "forbidden",
+        MisspellingKind::Replacement => "replacement",
+    }
+}
+
+/// Suggests corrections for the misspellings found in `text`, read from the file at `path`, and
+/// describes them for the report.
+pub fn collect(
+    path: &Path,
+    text: &str,
+    spellchecker: &Spellchecker,
+    misspellings: Vec<Misspelling>,
+) -> Vec<MisspellingRecord> {
+    let lines = LineIndex::new(text);
+
+    misspellings
+        .into_iter()
+        .map(|mut misspelling| {
+            spellchecker.suggest_misspelling(&mut misspelling);
+            // The suggestions for the source strings of translation files are ranked by the
+            // source language's dictionary
+            let checker = spellchecker.checker_for(&misspelling);
+            let word = misspelling.get_word();
+            let (start, end) = (misspelling.get_start(), misspelling.get_end() + 1);
+
+            let suggestions = misspelling
+                .get_suggestions()
+                .iter()
+                .map(|suggestion| SuggestionRecord {
+                    word: suggestion.clone(),
+                    distance: transposition_distance(
+                        &word.to_lowercase(),
+                        &suggestion.to_lowercase(),
+                    ),
+                    popularity: checker.dictionaries().popularity(suggestion),
+                    score: checker.suggestion_score(word, suggestion),
+                })
+                .collect();
+
+            MisspellingRecord {
+                path: path.display().to_string(),
+                word: word.clone(),
+                kind: misspelling.get_kind(),
+                byte_range: Range { start, end },
+                range: Range {
+                    start: lines.position(start),
+                    end: lines.position(end),
+                },
+                suggestions,
+            }
+        })
+        .collect()
+}
+
+/// Writes the report of the misspellings found in `files` files.
+pub fn write(
+    out: &mut impl Write,
+    format: OutputFormat,
+    misspellings: &[MisspellingRecord],
+    files: usize,
+) -> io::Result<()> {
+    let summary = Summary {
+        files,
+        misspellings: misspellings.len(),
+    };
+
+    match format {
+        OutputFormat::Text => {
+            for misspelling in misspellings {
+                writeln!(out, "{}", describe(misspelling))?;
+            }
+        }
+        OutputFormat::Json => {
+            let report = serde_json::json!({
+                "schema_version": SCHEMA_VERSION,
+                "misspellings": misspellings,
+                "summary": summary,
+            });
+            serde_json::to_writer_pretty(&mut *out, &report)?;
+            writeln!(out)?;
+        }
+        OutputFormat::JsonLines => {
+            for misspelling in misspellings {
+                write_line(out, "misspelling", misspelling)?;
+            }
+            write_line(out, "summary", &summary)?;
+        }
+        OutputFormat::Sarif => {
+            serde_json::to_writer_pretty(&mut *out, &sarif(misspellings))?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Builds the SARIF log of the misspellings
+fn sarif(misspellings: &[MisspellingRecord]) -> serde_json::Value {
+    let rules: Vec<serde_json::Value> = RULES
+        .iter()
+        .map(|(kind, description)| {
+            serde_json::json!({
+                "id": kind_name(*kind),
+                "shortDescription": { "text": description },
+                "defaultConfiguration": { "level": "warning" },
+            })
+        })
+        .collect();
+
+    let results: Vec<serde_json::Value> = misspellings
+        .iter()
+        .map(|misspelling| {
+            let rule_index = RULES
+                .iter()
+                .position(|(kind, _)| *kind == misspelling.kind)
+                .unwrap_or(0);
+            let region = serde_json::json!({
+                "startLine": misspelling.range.start.line,
+                "startColumn": misspelling.range.start.column,
+                "endLine": misspelling.range.end.line,
+                "endColumn": misspelling.range.end.column,
+            });
+            let artifact = serde_json::json!({ "uri": uri(&misspelling.path) });
+
+            let fixes: Vec<serde_json::Value> = misspelling
+                .suggestions
+                .iter()
+                .take(SARIF_FIXES)
+                .map(|suggestion| {
+                    let correction = correction_for(&misspelling.word, &suggestion.word);
+                    serde_json::json!({
+                        "description": { "text": format!("Replace with '{correction}'") },
+                        "artifactChanges": [{
+                            "artifactLocation": artifact,
+                            "replacements": [{
+                                "deletedRegion": region,
+                                "insertedContent": { "text": correction },
+                            }],
+                        }],
+                    })
+                })
+                .collect();
+
+            serde_json::json!({
+                "ruleId": kind_name(misspelling.kind),
+                "ruleIndex": rule_index,
+                "level": "warning",
+                "message": { "text": message(misspelling) },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": artifact,
+                        "region": region,
+                    },
+                }],
+                "fixes": fixes,
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "spelchek",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/chupsondev/spelchek.rs",
+                    "rules": rules,
+                },
+            },
+            // Columns count characters, like in the other formats
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }],
+    })
+}
+
+/// The message of a SARIF result
+fn message(misspelling: &MisspellingRecord) -> String {
+    let word = &misspelling.word;
+    let suggestions: Vec<&str> = misspelling
+        .suggestions
+        .iter()
+        .take(SARIF_FIXES)
+        .map(|suggestion| suggestion.word.as_str())
+        .collect();
+
+    let problem = match misspelling.kind {
+        MisspellingKind::Capitalization => format!("'{word}' is written in the wrong case"),
+        MisspellingKind::Forbidden => return format!("'{word}' is forbidden by the style guide"),
+        MisspellingKind::Replacement => {
+            format!("the style guide prefers a different term than '{word}'")
+        }
+        MisspellingKind::Spelling => format!("'{word}' is misspelled"),
+    };
+    match suggestions.is_empty() {
+        true => problem,
+        false => format!("{problem}, did you mean '{}'?", suggestions.join("', '")),
+    }
+}
+
+/// The path as a URI, which SARIF uses for artifact locations: a relative reference for relative
+/// paths, and a `file://` URI for absolute ones (like the files outside the working directory)
+fn uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut uri = String::with_capacity(path.len() + 8);
+
+    // Windows paths start with a drive letter, whose colon is kept
+    let drive = match path.as_bytes() {
+        [letter, b':', b'/', ..] if letter.is_ascii_alphabetic() => Some(&path[..2]),
+        _ => None,
+    };
+    let path = match drive {
+        Some(drive) => {
+            uri.push_str("file:///");
+            uri.push_str(drive);
+            &path[2..]
+        }
+        None if path.starts_with('/') => {
+            uri.push_str("file://");
+            &path
+        }
+        None => &path,
+    };
+
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
+
+/// Writes the object as a line of JSON, tagged with its type and the schema version
+fn write_line(out: &mut impl Write, kind: &str, object: &impl Serialize) -> io::Result<()> {
+    let mut value = serde_json::to_value(object)?;
+    if let serde_json::Value::Object(fields) = &mut value {
+        let mut tagged = serde_json::Map::new();
+        tagged.insert("type".to_string(), kind.into());
+        tagged.insert("schema_version".to_string(), SCHEMA_VERSION.into());
+        tagged.append(fields);
+        value = serde_json::Value::Object(tagged);
+    }
+    serde_json::to_writer(&mut *out, &value)?;
+    writeln!(out)
+}
+
+/// The `path:line:col: word (suggestions: ...)` line of the text format
+fn describe(misspelling: &MisspellingRecord) -> String {
+    let location = format!(
+        "{}:{}:{}",
+        misspelling.path, misspelling.range.start.line, misspelling.range.start.column
+    );
+    let word = &misspelling.word;
+
+    if misspelling.kind == MisspellingKind::Forbidden {
+        return format!("{location}: {word} (forbidden by the style guide)");
+    }
+    if misspelling.suggestions.is_empty() {
+        return format!("{location}: {word}");
+    }
+    let shown: Vec<&str> = misspelling
+        .suggestions
+        .iter()
+        .take(PRINTED_SUGGESTIONS)
+        .map(|suggestion| suggestion.word.as_str())
+        .collect();
+    format!("{location}: {word} (suggestions: {})", shown.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spellchecker::tokenizer::PlainText;
+    use crate::test_support::test_dictionary;
+
+    fn check(text: &str) -> Vec<MisspellingRecord> {
+        let dict = test_dictionary();
+        let spellchecker = Spellchecker::with_dictionary(dict);
+        let misspellings = spellchecker.find_misspellings(text, &PlainText);
+        collect(Path::new("notes.txt"), text, &spellchecker, misspellings)
+    }
+
+    fn write_to_string(format: OutputFormat, misspellings: &[MisspellingRecord]) -> String {
+        let mut out: Vec<u8> = Vec::new();
+        write(&mut out, format, misspellings, 1).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_line_index() {
+        let text = "one\nzażółć two\n\nthree";
+        let lines = LineIndex::new(text);
+        let position = |line, column| Position { line, column };
+        assert_eq!(lines.position(0), position(1, 1));
+        assert_eq!(lines.position(2), position(1, 3));
+        assert_eq!(lines.position(4), position(2, 1));
+        assert_eq!(lines.position(text.find("two").unwrap()), position(2, 8));
+        assert_eq!(lines.position(text.find("three").unwrap()), position(4, 1));
+    }
+
+    #[test]
+    fn test_collecting_misspellings() {
+        let misspellings = check("Thsi is\nsome txet");
+        assert_eq!(misspellings.len(), 2);
+
+        let txet = &misspellings[1];
+        assert_eq!(txet.word, "txet");
+        assert_eq!(txet.kind, MisspellingKind::Spelling);
+        assert_eq!(txet.byte_range, Range { start: 13, end: 17 });
+        assert_eq!(txet.range.start, Position { line: 2, column: 6 });
+        assert_eq!(
+            txet.range.end,
+            Position {
+                line: 2,
+                column: 10
+            }
+        );
+        // A swap of two letters is a single typo
+        assert_eq!(txet.suggestions[0].word, "text");
+        assert_eq!(txet.suggestions[0].distance, 1);
+        assert_eq!(txet.suggestions[0].popularity, Some(10));
+
+        // The suggestions are in the order of their scores
+        assert!(txet
+            .suggestions
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+    }
+
+    #[test]
+    fn test_text_format() {
+        let out = write_to_string(OutputFormat::Text, &check("Thsi is\nsome txet, qqqqqqqq"));
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("notes.txt:1:1: Thsi (suggestions: this, "));
+        assert!(lines[1].starts_with("notes.txt:2:6: txet (suggestions: text, "));
+        assert!(lines[2].starts_with("notes.txt:2:12: qqqqqqqq"));
+    }
+
+    #[test]
+    fn test_json_formats() {
+        let misspellings = check("Thsi is some txet");
+
+        let report: serde_json::Value =
+            serde_json::from_str(&write_to_string(OutputFormat::Json, &misspellings)).unwrap();
+        assert_eq!(report["schema_version"], SCHEMA_VERSION);
+        assert_eq!(report["misspellings"][1]["word"], "txet");
+        assert_eq!(report["misspellings"][1]["path"], "notes.txt");
+        assert_eq!(report["misspellings"][1]["kind"], "spelling");
+        assert_eq!(report["misspellings"][1]["range"]["start"]["column"], 14);
+        assert_eq!(report["summary"]["misspellings"], 2);
+
+        let out = write_to_string(OutputFormat::JsonLines, &misspellings);
+        let lines: Vec<serde_json::Value> = out
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["type"], "misspelling");
+        assert_eq!(lines[0]["word"], "Thsi");
+        assert_eq!(lines[2]["type"], "summary");
+        assert_eq!(lines[2]["schema_version"], SCHEMA_VERSION);
+        assert_eq!(lines[2]["files"], 1);
+    }
+
+    #[test]
+    fn test_sarif_format() {
+        let misspellings = check(
+            "Thsi is
+some txet",
+        );
+        let log: serde_json::Value =
+            serde_json::from_str(&write_to_string(OutputFormat::Sarif, &misspellings)).unwrap();
+
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "spelchek");
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "spelling");
+
+        let result = &run["results"][1];
+        assert_eq!(result["ruleId"], "spelling");
+        assert_eq!(
+            result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "notes.txt"
+        );
+        let region = &result["locations"][0]["physicalLocation"]["region"];
+        assert_eq!(region["startLine"], 2);
+        assert_eq!(region["startColumn"], 6);
+        assert_eq!(region["endColumn"], 10);
+        assert!(result["message"]["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("'txet' is misspelled, did you mean 'text'"));
+
+        // The fixes keep the case of the misspelled word
+        let fix = &run["results"][0]["fixes"][0]["artifactChanges"][0]["replacements"][0];
+        assert_eq!(fix["insertedContent"]["text"], "This");
+        assert_eq!(fix["deletedRegion"]["startColumn"], 1);
+    }
+
+    #[test]
+    fn test_choosing_the_output() {
+        let build = |args: &[&str]| {
+            let data_dir = std::env::temp_dir().join("spelchek-test-no-data-dir");
+            let mut full = vec!["--data-dir".to_string(), data_dir.display().to_string()];
+            full.extend(args.iter().map(|arg| arg.to_string()));
+            crate::Config::build(&full)
+        };
+
+        // `--output` chooses the report, leaving `--format` for the document
+        let config =
+            build(&["check", "--output", "sarif", "--format", "po", "Cargo.toml"]).unwrap();
+        assert_eq!(config.output_format, OutputFormat::Sarif);
+        assert_eq!(config.format.as_deref(), Some("po"));
+
+        let Err(error) = build(&["check", "--output", "xml", "Cargo.toml"]) else {
+            panic!("an unknown output format was accepted");
+        };
+        assert!(error.to_string().contains("unknown output format 'xml'"));
+        assert!(build(&["--output", "json", "Cargo.toml"]).is_err());
+    }
+
+    #[test]
+    fn test_kind_names() {
+        // The SARIF rules and the LSP diagnostics name the kinds like the JSON report does
+        for (kind, _) in RULES {
+            assert_eq!(serde_json::to_value(kind).unwrap(), kind_name(kind));
+        }
+    }
+
+    #[test]
+    fn test_threshold() {
+        assert!(!Threshold::default().fails(0));
+        assert!(Threshold::default().fails(1));
+
+        let max = Threshold {
+            max: 2,
+            warn_only: false,
+        };
+        assert!(!max.fails(2));
+        assert!(max.fails(3));
+
+        let warn_only = Threshold {
+            max: 0,
+            warn_only: true,
+        };
+        assert!(!warn_only.fails(100));
+    }
+
+    #[test]
+    fn test_uri() {
+        assert_eq!(uri("docs/notes.txt"), "docs/notes.txt");
+        assert_eq!(uri("my notes/żółw.md"), "my%20notes/%C5%BC%C3%B3%C5%82w.md");
+        assert_eq!(uri("/home/me/a b.txt"), "file:///home/me/a%20b.txt");
+        assert_eq!(uri("C:\\Users\\me\\a.txt"), "file:///C:/Users/me/a.txt");
+    }
+}
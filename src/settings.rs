@@ -0,0 +1,349 @@
+//! The configuration files, holding the settings which would otherwise have to be passed on the
+//! command line every time.
+//!
+//! The project's settings are read from the `.spelchek.toml` closest to the checked paths (found by
+//! walking up from them, or from the working directory when the text is read from stdin), which
+//! have to share it, and the user's from `config.toml` in the user directory. The project's
+//! settings take precedence over the user's, and the command line options over both:
+//!
+//! ```toml
+//! lang = "en_GB"                  # the dictionary to use, like --lang
+//! dict = "words/dict.txt"         # like --dict, and so are forbidden, replacements, phrases
+//!                                 # and corrections
+//! include = ["*.md", "*.txt"]     # the files checked in the directories, like --include
+//! exclude = ["CHANGELOG.md"]      # like --exclude
+//! suggestions = 5                 # how many suggestions are offered
+//! max-distance = 3                # how many typos away from the misspelling suggestions can be
+//! min-popularity = 1000000        # like --min-popularity
+//! popularity-margin = 50          # like --popularity-margin
+//! po-source-lang = "en_US"        # like --po-source-lang
+//!
+//! [formats]                       # the formats of the files with these extensions
+//! pot = "po"
+//! ```
+//!
+//! Relative paths are relative to the directory of the configuration file.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+
+use crate::paths::find_upwards;
+use crate::prelude::*;
+
+/// The name of the project's configuration file
+pub const CONFIG_FILE_NAME: &str = ".spelchek.toml";
+/// The name of the user's configuration file, in the user directory
+pub const USER_CONFIG_FILE_NAME: &str = "config.toml";
+
+/// The settings read from the configuration files. The ones which aren't set are `None` (or
+/// empty).
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Settings {
+    pub lang: Option<String>,
+    pub dict: Option<PathBuf>,
+    pub forbidden: Option<PathBuf>,
+    pub replacements: Option<PathBuf>,
+    pub phrases: Option<PathBuf>,
+    pub corrections: Option<PathBuf>,
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub formats: BTreeMap<String, String>, // The format names by file extension
+    pub suggestions: Option<usize>,
+    pub max_distance: Option<usize>,
+    pub min_popularity: Option<i64>,
+    pub popularity_margin: Option<f64>,
+    pub po_source_lang: Option<String>,
+}
+
+impl Settings {
+    /// Reads the settings from the project's and the user's configuration files, if they exist.
+    /// `start` is the directory the project's file is looked for from.
+    pub fn discover(start: &Path, user_config: Option<&Path>) -> Result<Self> {
+        let project = match find_upwards(start, CONFIG_FILE_NAME) {
+            Some(path) => Settings::load(&path)?,
+            None => Settings::default(),
+        };
+        let user = match user_config.filter(|path| path.is_file()) {
+            Some(path) => Settings::load(path)?,
+            None => Settings::default(),
+        };
+        Ok(project.or(user))
+    }
+
+    /// Reads the settings from a configuration file, resolving the paths in it
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("couldn't read {}", path.display()))?;
+        let mut settings = Settings::parse(&text)
+            .with_context(|| format!("invalid configuration in {}", path.display()))?;
+
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for file in [
+            &mut settings.dict,
+            &mut settings.forbidden,
+            &mut settings.replacements,
+            &mut settings.phrases,
+            &mut settings.corrections,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *file = dir.join(&*file);
+        }
+        Ok(settings)
+    }
+
+    /// Parses and validates the contents of a configuration file
+    pub fn parse(text: &str) -> Result<Self> {
+        let settings: Settings = toml::from_str(text)?;
+
+        if settings.suggestions == Some(0) {
+            return Err(anyhow!("`suggestions` has to be at least 1"));
+        }
+        if settings.min_popularity.is_some_and(|min| min < 0) {
+            return Err(anyhow!("`min-popularity` can't be negative"));
+        }
+        if settings
+            .popularity_margin
+            .is_some_and(|margin| margin < 1.0)
+        {
+            return Err(anyhow!("`popularity-margin` has to be at least 1"));
+        }
+        // The format names are only checked when a file is checked, as library users can
+        // register formats of their own
+        for extension in settings.formats.keys() {
+            if extension.is_empty() || extension.starts_with('.') {
+                return Err(anyhow!(
+                    "`formats.{extension}` has to be a file extension without the leading dot"
+                ));
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Returns these settings, with the ones which aren't set taken from `fallback`
+    pub fn or(self, fallback: Settings) -> Self {
+        let mut formats = fallback.formats;
+        formats.extend(self.formats);
+        Settings {
+            lang: self.lang.or(fallback.lang),
+            dict: self.dict.or(fallback.dict),
+            forbidden: self.forbidden.or(fallback.forbidden),
+            replacements: self.replacements.or(fallback.replacements),
+            phrases: self.phrases.or(fallback.phrases),
+            corrections: self.corrections.or(fallback.corrections),
+            include: match self.include.is_empty() {
+                true => fallback.include,
+                false => self.include,
+            },
+            exclude: [fallback.exclude, self.exclude].concat(),
+            formats,
+            suggestions: self.suggestions.or(fallback.suggestions),
+            max_distance: self.max_distance.or(fallback.max_distance),
+            min_popularity: self.min_popularity.or(fallback.min_popularity),
+            popularity_margin: self.popularity_margin.or(fallback.popularity_margin),
+            po_source_lang: self.po_source_lang.or(fallback.po_source_lang),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_dir;
+
+    #[test]
+    fn test_parsing() {
+        let settings = Settings::parse(
+            "lang = \"en_GB\"\nexclude = [\"CHANGELOG.md\"]\nsuggestions = 5\n\
+             max-distance = 2\n[formats]\npot = \"po\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            settings,
+            Settings {
+                lang: Some("en_GB".to_string()),
+                exclude: vec!["CHANGELOG.md".to_string()],
+                formats: BTreeMap::from([("pot".to_string(), "po".to_string())]),
+                suggestions: Some(5),
+                max_distance: Some(2),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        let error = |text: &str| format!("{:#}", Settings::parse(text).unwrap_err());
+
+        // The errors name the key, and the line it is on when the file can't be read
+        let unknown = error("lang = \"en\"\nsugestions = 5\n");
+        assert!(unknown.contains("line 2") && unknown.contains("sugestions"));
+        let wrong_type = error("suggestions = \"five\"\n");
+        assert!(wrong_type.contains("line 1") && wrong_type.contains("suggestions"));
+        assert!(error("suggestions = 0\n").contains("`suggestions`"));
+        assert!(error("popularity-margin = 0.5\n").contains("`popularity-margin`"));
+        assert!(error("[formats]\n\".pot\" = \"po\"\n").contains("`formats..pot`"));
+    }
+
+    #[test]
+    fn test_discovering() {
+        let root = test_dir("settings");
+        fs::create_dir_all(root.join("project/docs")).unwrap();
+        fs::write(
+            root.join("project").join(CONFIG_FILE_NAME),
+            "dict = \"words.txt\"\nexclude = [\"drafts\"]\n",
+        )
+        .unwrap();
+        let user_config = root.join(USER_CONFIG_FILE_NAME);
+        fs::write(
+            &user_config,
+            "dict = \"/usr/share/words\"\nexclude = [\"*.log\"]\nsuggestions = 3\n",
+        )
+        .unwrap();
+
+        // The project's settings come first, and its paths are relative to its directory
+        let settings = Settings::discover(&root.join("project/docs"), Some(&user_config)).unwrap();
+        assert_eq!(settings.dict, Some(root.join("project/words.txt")));
+        assert_eq!(settings.exclude, ["*.log", "drafts"]);
+        assert_eq!(settings.suggestions, Some(3));
+
+        // Errors say which file is wrong
+        fs::write(&user_config, "suggestions = -1\n").unwrap();
+        let error = Settings::discover(&root, Some(&user_config)).unwrap_err();
+        assert!(format!("{error:#}").contains(&user_config.display().to_string()));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_formats_registered_later() {
+        let root = test_dir("formats");
+        fs::write(
+            root.join(CONFIG_FILE_NAME),
+            "[formats]\npot = \"gettext\"\n",
+        )
+        .unwrap();
+        let file = root.join("messages.pot");
+        fs::write(&file, "").unwrap();
+
+        let args: Vec<String> = [
+            "check",
+            "--data-dir",
+            &root.join("data").display().to_string(),
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .chain([file.display().to_string()])
+        .collect();
+        let Ok(mut config) = crate::Config::build(&args) else {
+            panic!("the unknown format was rejected before the formats were registered");
+        };
+
+        // The format is only missing until it is registered
+        let Err(error) = config.tokenizer_for(&file) else {
+            panic!("the unknown format was used");
+        };
+        assert!(error
+            .to_string()
+            .contains("`formats.pot`: unknown format 'gettext'"));
+        config.tokenizers_mut().register(
+            "gettext",
+            &[],
+            std::sync::Arc::new(crate::spellchecker::tokenizer::PlainText),
+        );
+        assert!(config.tokenizer_for(&file).is_ok());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_po_source_lang() {
+        let root = test_dir("po-source-lang");
+        let data_dir = root.join("data");
+        fs::create_dir_all(&data_dir).unwrap();
+        fs::write(data_dir.join("dict.txt"), "bonjour\n").unwrap();
+        fs::write(data_dir.join("suggestion_dict.txt"), "bonjour 1\n").unwrap();
+        fs::write(root.join(CONFIG_FILE_NAME), "po-source-lang = \"en_US\"\n").unwrap();
+        let file = root.join("fr.po");
+        fs::write(&file, "").unwrap();
+
+        let build = |extra: &[&str]| {
+            let args: Vec<String> = ["check", "--data-dir", &data_dir.display().to_string()]
+                .iter()
+                .chain(extra)
+                .map(|arg| arg.to_string())
+                .chain([file.display().to_string()])
+                .collect();
+            let Ok(config) = crate::Config::build(&args) else {
+                panic!("the arguments were rejected");
+            };
+            config
+        };
+
+        // The source language's dictionary is only needed when the source strings are checked
+        assert!(build(&[]).load_spellchecker().is_ok());
+        let Err(error) = build(&["--po-check-msgid"]).load_spellchecker() else {
+            panic!("the missing source language dictionary wasn't reported");
+        };
+        assert!(error
+            .to_string()
+            .contains("couldn't load the 'en_US' dictionary for the source strings"));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_paths_with_different_settings() {
+        let root = test_dir("different-settings");
+        for dir in ["first", "second", "first/docs"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(
+            root.join("first").join(CONFIG_FILE_NAME),
+            "suggestions = 3\n",
+        )
+        .unwrap();
+        let build = |paths: &[&str]| {
+            let args: Vec<String> = [
+                "check",
+                "--data-dir",
+                &root.join("data").display().to_string(),
+            ]
+            .into_iter()
+            .map(str::to_string)
+            .chain(
+                paths
+                    .iter()
+                    .map(|path| root.join(path).display().to_string()),
+            )
+            .collect();
+            crate::Config::build(&args)
+        };
+
+        // Paths sharing the file can be checked together, the others can't
+        assert!(build(&["first", "first/docs"]).is_ok());
+        let Err(error) = build(&["first/docs", "second"]) else {
+            panic!("paths with different settings were checked together");
+        };
+        let error = error.to_string();
+        assert!(error.contains(
+            &root
+                .join("first")
+                .join(CONFIG_FILE_NAME)
+                .display()
+                .to_string()
+        ));
+        assert!(error.contains(&format!("no {CONFIG_FILE_NAME}")));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
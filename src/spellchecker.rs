@@ -1,28 +1,64 @@
 pub mod algorithm;
+pub mod channel;
+pub mod dictionary;
+pub mod embedded;
+pub mod history;
+pub mod layers;
+pub mod phrases;
+pub mod po;
+pub mod style;
+pub mod tokenizer;
 use priority_queue::DoublePriorityQueue;
 use ratatui::text::Text;
+use serde::Serialize;
 
+use crate::paths::{project_words_path, Locations, HISTORY_FILE_NAME, USER_WORDS_FILE_NAME};
 use crate::prelude::*;
 use core::panic;
-use std::{cmp::Ordering, fs};
-
-use self::algorithm::edit_distance;
+use std::cmp::Ordering;
+use std::ops::Range;
+use std::path::Path;
+
+use self::channel::ErrorModel;
+use self::dictionary::{Dictionary, WordStatus};
+use self::history::CorrectionHistory;
+use self::layers::{DictionaryStack, LayerKind};
+use self::phrases::{PhraseList, PhraseMatch};
+use self::style::{StyleIssue, StyleRules};
+use self::tokenizer::{PlainText, Tokenizer};
 
 const NUMBER_OF_SUGGESTIONS: usize = 10;
 
-#[derive(Eq, Debug)]
+/// How many suggestions are offered for a misspelling, and how far from it they can be
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SuggestionLimits {
+    pub count: usize, // How many suggestions are offered at most
+    // How many typos away from the misspelling a suggestion can be, counting a swap of two letters
+    // as one. Corrections the user has accepted before are always suggested.
+    pub max_distance: Option<usize>,
+}
+
+impl Default for SuggestionLimits {
+    fn default() -> Self {
+        SuggestionLimits {
+            count: NUMBER_OF_SUGGESTIONS,
+            max_distance: None,
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct SuggestionPriority {
-    edit_distance: i32,
-    popularity: i64,
+    times_accepted: u32, // How many times the user corrected the misspelling to the suggestion
+    score: f64,          // The log probability of the suggestion being the intended word
 }
 
 impl Ord for SuggestionPriority {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.edit_distance.cmp(&other.edit_distance) {
-            Ordering::Equal => self.popularity.cmp(&other.popularity),
-            Ordering::Greater => Ordering::Less,
-            Ordering::Less => Ordering::Greater,
-        }
+        // Corrections the user has accepted before come first, no matter how unlikely they are
+        self.times_accepted
+            .cmp(&other.times_accepted)
+            .then_with(|| self.score.total_cmp(&other.score))
     }
 }
 
@@ -34,17 +70,54 @@ impl PartialOrd for SuggestionPriority {
 
 impl PartialEq for SuggestionPriority {
     fn eq(&self, other: &Self) -> bool {
-        self.edit_distance == other.edit_distance && self.popularity == other.popularity
+        self.cmp(other) == Ordering::Equal
     }
 }
 
+impl Eq for SuggestionPriority {}
+
 impl SuggestionPriority {
-    fn new(edit_distance: i32, popularity: i64) -> Self {
+    /// Scores the suggestion by the noisy channel model: the probability of the word, given by
+    /// its popularity, times the probability of it being typed as the misspelling.
+    fn new(model: &ErrorModel, misspelling: &str, suggestion: &str, popularity: i64) -> Self {
+        let prior = (popularity.max(0) as f64 + 1.0).ln();
         Self {
-            edit_distance,
-            popularity,
+            times_accepted: 0,
+            score: prior + model.log_likelihood(misspelling, suggestion),
         }
     }
+
+    /// Boosts the suggestion by the number of times the user has accepted it before
+    fn with_times_accepted(mut self, times_accepted: u32) -> Self {
+        self.times_accepted = times_accepted;
+        self
+    }
+}
+
+/// What is wrong with a misspelled word.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MisspellingKind {
+    /// The word isn't in the dictionary
+    #[default]
+    Spelling,
+    /// The word is in the dictionary, but has to be written in a different casing (like a proper
+    /// noun or an acronym)
+    Capitalization,
+    /// The word is banned by the style guide
+    Forbidden,
+    /// The style guide prefers a different term in place of the word
+    Replacement,
+}
+
+impl MisspellingKind {
+    /// Whether the word is flagged by the style guide rather than being misspelled
+    pub fn is_style_issue(&self) -> bool {
+        matches!(
+            self,
+            MisspellingKind::Forbidden | MisspellingKind::Replacement
+        )
+    }
 }
 
 /// The representation of a misspelling in the text. The start and end represent the positions in
@@ -54,7 +127,9 @@ pub struct Misspelling {
     word: String,
     start: usize,
     end: usize,
+    kind: MisspellingKind,
     suggestions: Vec<String>,
+    in_source: bool, // Whether it was found in the source language parts of the document
 }
 
 impl From<&Misspelling> for Text<'_> {
@@ -69,7 +144,9 @@ impl Misspelling {
             word,
             start,
             end,
+            kind: MisspellingKind::Spelling,
             suggestions: Vec::new(),
+            in_source: false,
         }
     }
 
@@ -78,7 +155,27 @@ impl Misspelling {
             word,
             start: range.0,
             end: range.1,
+            kind: MisspellingKind::Spelling,
             suggestions: Vec::new(),
+            in_source: false,
+        }
+    }
+
+    /// Creates a misspelling of the given kind, with suggestions known upfront (like the correct
+    /// casing of the word, or the term preferred by the style guide).
+    pub fn with_kind(
+        word: String,
+        range: (usize, usize),
+        kind: MisspellingKind,
+        suggestions: Vec<String>,
+    ) -> Self {
+        Self {
+            word,
+            start: range.0,
+            end: range.1,
+            kind,
+            suggestions,
+            in_source: false,
         }
     }
 
@@ -94,29 +191,80 @@ impl Misspelling {
     pub fn get_range(&self) -> (usize, usize) {
         (self.start, self.end)
     }
+    pub fn get_kind(&self) -> MisspellingKind {
+        self.kind
+    }
+
+    /// Whether the misspelling was found in a part of the document written in its source
+    /// language, like the source strings of a translation file
+    pub fn is_in_source_language(&self) -> bool {
+        self.in_source
+    }
+
+    /// Moves the misspelling by `offset` bytes, after the text before it has changed
+    pub fn offset(&mut self, offset: i32) {
+        // Shouldn't overflow, if it does, look for the error in caller
+        self.start = (self.start as i32 + offset) as usize;
+        self.end = (self.end as i32 + offset) as usize;
+    }
+
     pub fn get_suggestions(&self) -> &Vec<String> {
         &self.suggestions
     }
 
-    pub fn suggest(&mut self, dict: &Vec<String>) -> &Vec<String> {
-        let mut top_suggestions = DoublePriorityQueue::new();
-
-        for entry in dict {
-            let mut entry = entry.split_ascii_whitespace();
-            let word: &str = entry.next().unwrap();
-            let popularity: i64 = entry.next().unwrap().trim().parse().unwrap();
+    /// Finds the most likely corrections of the word among the words of all of the dictionaries,
+    /// putting the corrections accepted for it before first
+    pub fn suggest(
+        &mut self,
+        dicts: &DictionaryStack,
+        history: &CorrectionHistory,
+        model: &ErrorModel,
+    ) -> &Vec<String> {
+        self.suggest_within(dicts, history, model, SuggestionLimits::default())
+    }
 
-            // yes, I know this is terrible, I'll work on that
-            let dist = edit_distance(&self.word.to_lowercase(), word);
-            top_suggestions.push(word, SuggestionPriority::new(dist, popularity));
-            while top_suggestions.len() > NUMBER_OF_SUGGESTIONS {
+    /// Finds the most likely corrections of the word like [`Misspelling::suggest`], but only as
+    /// many and as close to the word as the limits allow
+    pub fn suggest_within(
+        &mut self,
+        dicts: &DictionaryStack,
+        history: &CorrectionHistory,
+        model: &ErrorModel,
+        limits: SuggestionLimits,
+    ) -> &Vec<String> {
+        let mut top_suggestions = DoublePriorityQueue::new();
+        let accepted = history.corrections_of(&self.word);
+        let misspelling = self.word.to_lowercase();
+        let length = misspelling.chars().count();
+
+        let suggestions = dicts
+            .layers()
+            .iter()
+            .flat_map(|layer| layer.dictionary().suggestions());
+        for (word, popularity) in suggestions {
+            let times_accepted = accepted.and_then(|accepted| accepted.get(word)).copied();
+            if let (Some(max), None) = (limits.max_distance, times_accepted) {
+                // The difference in length is a cheap lower bound of the distance
+                if word.chars().count().abs_diff(length) > max
+                    || algorithm::transposition_distance(&misspelling, &word.to_lowercase()) > max
+                {
+                    continue;
+                }
+            }
+            top_suggestions.push(
+                word,
+                SuggestionPriority::new(model, &misspelling, word, popularity)
+                    .with_times_accepted(times_accepted.unwrap_or(0)),
+            );
+            while top_suggestions.len() > limits.count {
                 top_suggestions.pop_min();
             }
         }
 
+        // Proper nouns and acronyms are suggested in their canonical casing
         self.suggestions = top_suggestions
             .into_sorted_iter()
-            .map(|x| x.0.to_owned())
+            .map(|x| dicts.canonical_form(x.0).to_owned())
             .rev()
             .collect();
         &self.suggestions
@@ -165,84 +313,293 @@ impl Misspelling {
 
 #[derive(Default, Debug)]
 pub struct Spellchecker {
-    dict: Vec<String>,
-    suggestion_dict: Vec<String>,
+    dicts: DictionaryStack,
+    style: StyleRules,
+    phrases: PhraseList,
+    history: CorrectionHistory,
+    model: ErrorModel,
+    limits: SuggestionLimits,
+    source: Option<Box<Spellchecker>>, // The spellchecker of the documents' source language
     pub misspellings: Vec<Misspelling>,
 }
 
 impl Spellchecker {
-    fn load_dict(name: &str) -> Result<Vec<String>> {
-        let dict_path = crate::get_program_files_path().join(name);
+    pub fn new() -> Result<Self> {
+        Spellchecker::load(&Locations::default())
+    }
 
-        let dict_content = fs::read(dict_path)?;
-        Ok(String::from_utf8_lossy(&dict_content)
-            .into_owned()
-            .lines()
-            .map(|word| word.trim().to_string())
-            .collect())
+    /// Creates a spellchecker with the dictionary found at the passed locations, together with
+    /// the user's personal word list and correction history
+    pub fn load(locations: &Locations) -> Result<Self> {
+        let mut spellchecker = Spellchecker::load_without_user_files(locations)?;
+        if let Ok(user_dir) = locations.user_dir() {
+            spellchecker
+                .dicts
+                .load_word_list(LayerKind::User, &user_dir.join(USER_WORDS_FILE_NAME))?;
+            spellchecker.history = CorrectionHistory::load(&user_dir.join(HISTORY_FILE_NAME))?;
+        }
+        // The error model is trained on the corpus of corrections and the user's own ones
+        for (misspelling, correction, times) in spellchecker.history.pairs() {
+            for _ in 0..times {
+                spellchecker.model.learn(misspelling, correction);
+            }
+        }
+        Ok(spellchecker)
     }
 
-    pub fn new() -> Result<Self> {
-        let dict = Spellchecker::load_dict("dict.txt")?;
-        let suggestion_dict = Spellchecker::load_dict("suggestion_dict.txt")?;
+    /// Creates a spellchecker with the dictionary found at the passed locations, but without the
+    /// user's personal word list and correction history, so that it works the same for everyone
+    pub fn load_without_user_files(locations: &Locations) -> Result<Self> {
+        let paths = locations.resolve_dictionary()?;
+        let dicts = DictionaryStack::new(Dictionary::load(&paths)?);
+        let model = ErrorModel::load(paths.corrections.as_deref())?;
+        let style = StyleRules::load(paths.forbidden.as_deref(), paths.replacements.as_deref())?;
+        let phrases = PhraseList::load(paths.phrases.as_deref())?;
 
         Ok(Spellchecker {
-            dict,
-            suggestion_dict,
+            dicts,
+            style,
+            phrases,
+            history: CorrectionHistory::default(),
+            model,
+            limits: SuggestionLimits::default(),
+            source: None,
             misspellings: Vec::new(),
         })
     }
 
-    /// Returns `true` if the passed character is considered one that separates words
-    fn separates_word(character: &char) -> bool {
-        match character {
-            '.' | ':' | ',' | '!' | '?' | ';' | '&' => true,
-            c if c.is_whitespace() => true,
-            _ => false,
+    /// Creates a spellchecker using the passed dictionary
+    pub fn with_dictionary(dict: Dictionary) -> Self {
+        Spellchecker {
+            dicts: DictionaryStack::new(dict),
+            style: StyleRules::default(),
+            phrases: PhraseList::default(),
+            history: CorrectionHistory::default(),
+            model: ErrorModel::default(),
+            limits: SuggestionLimits::default(),
+            source: None,
+            misspellings: Vec::new(),
         }
     }
 
-    pub fn check(&mut self, buffer: &str) {
-        let mut word_buf: String = String::new(); // The currently considered word
+    /// Adds the word list of the project the checked file belongs to, found by walking up from
+    /// the file
+    pub fn load_project_words(&mut self, checked_file: &Path) -> Result<()> {
+        if let Some(source) = &mut self.source {
+            source.load_project_words(checked_file)?;
+        }
+        self.dicts
+            .load_word_list(LayerKind::Project, &project_words_path(checked_file))
+    }
 
-        let mut is_proper_word: bool = true; // Whether the word contains any invalid characters
-                                             // like numbers
+    pub fn dictionaries(&self) -> &DictionaryStack {
+        &self.dicts
+    }
 
-        let mut start_pos: usize = 0; // The position of the first character in the currently
-                                      // considered word
+    pub fn phrases(&self) -> &PhraseList {
+        &self.phrases
+    }
 
-        for (i, c) in buffer
-            .chars()
-            .chain(vec!['\n'].into_iter()) // A new line character is added to the back, so that the last word in the buffer is processed
-            .enumerate()
-        {
-            if Spellchecker::separates_word(&c) {
-                if !word_buf.is_empty() && is_proper_word {
-                    self.check_word_and_add(&word_buf, (start_pos, i - 1));
-                }
+    /// Adds the word to one of the dictionary layers, and drops the misspellings it makes correct
+    pub fn add_word(&mut self, layer: LayerKind, word: &str) -> Result<()> {
+        self.dicts.add_word(layer, word)?;
 
-                // Reset variables
-                word_buf.clear();
-                start_pos = i + 1;
-                is_proper_word = true;
-                continue;
+        // The word lists are shared with the source language, so the word is added to its layer
+        // too, without writing it to the file again
+        if let Some(source) = &mut self.source {
+            match self.dicts.layer(layer).and_then(|layer| layer.path.clone()) {
+                Some(path) => source.dicts.load_word_list(layer, &path)?,
+                None => source.dicts.add_word(layer, word)?,
             }
+        }
+
+        let mut misspellings = std::mem::take(&mut self.misspellings);
+        misspellings.retain(|misspelling| {
+            misspelling.kind.is_style_issue()
+                || self
+                    .checker_for(misspelling)
+                    .dicts
+                    .lookup(&misspelling.word)
+                    .0
+                    != WordStatus::Correct
+        });
+        self.misspellings = misspellings;
+        Ok(())
+    }
+
+    /// Sets the style guide rules checked along with the spelling
+    pub fn set_style_rules(&mut self, style: StyleRules) {
+        self.style = style;
+    }
+
+    /// Sets the history of accepted corrections used to rank the suggestions
+    pub fn set_history(&mut self, history: CorrectionHistory) {
+        self.history = history;
+    }
 
-            word_buf.push(c);
+    /// Sets how many suggestions are offered, and how far from the misspelling they can be
+    pub fn set_suggestion_limits(&mut self, limits: SuggestionLimits) {
+        self.limits = limits;
+    }
+
+    /// Remembers that the user corrected the misspelled word to `correction`, so that it is
+    /// suggested first next time
+    pub fn record_correction(&mut self, misspelling: &str, correction: &str) -> Result<()> {
+        self.model.learn(misspelling, correction);
+        self.history.record(misspelling, correction)
+    }
 
-            is_proper_word =
-                is_proper_word && (c.is_alphabetic() || c == '\u{0027}' || c == '\u{2019}');
-            // Allow apostrophes inside of words
+    /// Sets the model of typing errors used to rank the suggestions
+    pub fn set_error_model(&mut self, model: ErrorModel) {
+        self.model = model;
+    }
+
+    /// Sets the multi-word phrases recognised in the text
+    pub fn set_phrases(&mut self, phrases: PhraseList) {
+        self.phrases = phrases;
+    }
+
+    /// Sets the spellchecker of the source language, which checks the parts of the documents
+    /// written in it, like the source strings of translation files. Without one, they are
+    /// checked with this spellchecker's dictionary.
+    pub fn set_source_language(&mut self, source: Spellchecker) {
+        self.source = Some(Box::new(source));
+    }
+
+    /// Returns the spellchecker the misspelling was found with: the source language's one if the
+    /// misspelling is in the source language, otherwise this one. Its dictionaries are the ones
+    /// to look the misspelled word and the suggestions up in.
+    pub fn checker_for(&self, misspelling: &Misspelling) -> &Spellchecker {
+        match &self.source {
+            Some(source) if misspelling.in_source => source,
+            _ => self,
         }
     }
 
-    fn check_word_and_add(&mut self, word: &str, range: (usize, usize)) {
-        if algorithm::is_word_correct(word, &self.dict) {
+    pub fn check(&mut self, buffer: &str) {
+        self.check_with(buffer, &PlainText);
+    }
+
+    /// Checks the words of the buffer found by the passed tokenizer, so that only the parts of a
+    /// document that are actual text are spellchecked.
+    pub fn check_with(&mut self, buffer: &str, tokenizer: &dyn Tokenizer) {
+        let found = self.find_misspellings(buffer, tokenizer);
+        self.misspellings.extend(found);
+    }
+
+    /// Returns the misspellings of the buffer without keeping them, so that many texts can be
+    /// checked at the same time.
+    pub fn find_misspellings(&self, buffer: &str, tokenizer: &dyn Tokenizer) -> Vec<Misspelling> {
+        let mut misspellings = self.find_in_words(buffer, &tokenizer.tokenize(buffer));
+
+        let source_words = tokenizer.tokenize_source(buffer);
+        if !source_words.is_empty() {
+            let source = self.source.as_deref().unwrap_or(self);
+            let found = source.find_in_words(buffer, &source_words);
+            misspellings.extend(found.into_iter().map(|mut misspelling| {
+                misspelling.in_source = self.source.is_some();
+                misspelling
+            }));
+            misspellings.sort_by_key(|misspelling| misspelling.start);
+        }
+        misspellings
+    }
+
+    /// Returns the misspellings among the passed words of the buffer
+    fn find_in_words(&self, buffer: &str, words: &[Range<usize>]) -> Vec<Misspelling> {
+        let mut misspellings = Vec::new();
+
+        let mut i: usize = 0;
+        while i < words.len() {
+            let dicts = &self.dicts;
+            let is_unknown = |word: &str| dicts.lookup(word).0 == WordStatus::Unknown;
+
+            // The kind of the error in the phrase and the phrase itself, if there is one
+            let (len, error) = match self.phrases.match_at(buffer, &words[i..], &is_unknown) {
+                None => {
+                    self.check_token(&buffer[words[i].clone()], words[i].start, &mut misspellings);
+                    i += 1;
+                    continue;
+                }
+                Some(PhraseMatch::Correct { len }) => (len, None),
+                Some(PhraseMatch::WrongCase { len, phrase }) => (
+                    len,
+                    Some((MisspellingKind::Capitalization, phrase.to_string())),
+                ),
+                Some(PhraseMatch::Misspelled { len, phrase }) => {
+                    (len, Some((MisspellingKind::Spelling, phrase.to_string())))
+                }
+            };
+
+            // Errors in a phrase are reported and corrected as a whole
+            if let Some((kind, phrase)) = error {
+                let (start, end) = (words[i].start, words[i + len - 1].end);
+                misspellings.push(Misspelling::with_kind(
+                    buffer[start..end].to_string(),
+                    (start, end - 1),
+                    kind,
+                    vec![phrase],
+                ));
+            }
+            i += len;
+        }
+        misspellings
+    }
+
+    /// Checks a token starting at `start`. Hyphenated compounds which aren't known as a whole are
+    /// correct if all of their parts are.
+    fn check_token(&self, token: &str, start: usize, misspellings: &mut Vec<Misspelling>) {
+        if !token.contains('-')
+            || self.style.is_flagged(token)
+            || self.dicts.lookup(token).0 != WordStatus::Unknown
+        {
+            self.check_word_and_add(token, (start, start + token.len() - 1), misspellings);
             return;
         }
 
-        self.misspellings
-            .push(Misspelling::from_range(word.to_string(), range));
+        let mut part_start = start;
+        for part in token.split('-') {
+            if !part.is_empty() {
+                self.check_word_and_add(
+                    part,
+                    (part_start, part_start + part.len() - 1),
+                    misspellings,
+                );
+            }
+            part_start += part.len() + 1;
+        }
+    }
+
+    fn check_word_and_add(
+        &self,
+        word: &str,
+        range: (usize, usize),
+        misspellings: &mut Vec<Misspelling>,
+    ) {
+        let word_string = word.to_string();
+        let misspelling = match self.style.check(word) {
+            Some(StyleIssue::Forbidden) => {
+                Misspelling::with_kind(word_string, range, MisspellingKind::Forbidden, Vec::new())
+            }
+            Some(StyleIssue::Replace(preferred)) => Misspelling::with_kind(
+                word_string,
+                range,
+                MisspellingKind::Replacement,
+                vec![preferred.to_string()],
+            ),
+            None => match self.dicts.lookup(word).0 {
+                WordStatus::Correct => return,
+                WordStatus::WrongCase(forms) => Misspelling::with_kind(
+                    word_string,
+                    range,
+                    MisspellingKind::Capitalization,
+                    forms,
+                ),
+                WordStatus::Unknown => Misspelling::from_range(word_string, range),
+            },
+        };
+
+        misspellings.push(misspelling);
     }
 
     pub fn suggest(&mut self, misspelling_index: usize) {
@@ -250,12 +607,53 @@ impl Spellchecker {
             .misspellings
             .get_mut(misspelling_index)
             .unwrap_or_else(|| panic!("wrong selected misspelling index"));
+        let mut misspelling = std::mem::replace(misspelling, Misspelling::new(String::new(), 0, 0));
+        self.suggest_misspelling(&mut misspelling);
+        self.misspellings[misspelling_index] = misspelling;
+    }
 
+    /// Finds the suggestions for a misspelling returned by [`Spellchecker::find_misspellings`],
+    /// unless it already has some
+    pub fn suggest_misspelling(&self, misspelling: &mut Misspelling) {
         if !misspelling.suggestions.is_empty() {
             return;
         }
+        if let Some(source) = self.source.as_deref().filter(|_| misspelling.in_source) {
+            source.suggest_misspelling(misspelling);
+            return;
+        }
+
+        misspelling.suggest_within(&self.dicts, &self.history, &self.model, self.limits);
+
+        // Never suggest what the style guide doesn't allow
+        let style = &self.style;
+        misspelling
+            .suggestions
+            .retain(|suggestion| !style.is_flagged(suggestion));
+    }
+
+    /// Returns the score `suggestion` is ranked by among the suggestions for `misspelling`: the log
+    /// probability of it being the intended word, by the noisy channel model. Corrections the user
+    /// has accepted before are ranked first regardless of their score.
+    pub fn suggestion_score(&self, misspelling: &str, suggestion: &str) -> f64 {
+        let popularity = self.dicts.popularity(suggestion).unwrap_or(0);
+        SuggestionPriority::new(
+            &self.model,
+            &misspelling.to_lowercase(),
+            &suggestion.to_lowercase(),
+            popularity,
+        )
+        .score
+    }
+
+    /// Finds the suggestions for a word which isn't one of the misspellings found in the text
+    pub fn suggestions_for(&self, word: &str) -> Vec<String> {
+        let mut misspelling = Misspelling::new(word.to_string(), 0, 0);
+        misspelling.suggest_within(&self.dicts, &self.history, &self.model, self.limits);
 
-        misspelling.suggest(&self.suggestion_dict);
+        let mut suggestions = misspelling.suggestions;
+        suggestions.retain(|suggestion| !self.style.is_flagged(suggestion));
+        suggestions
     }
 
     pub fn get_suggestions(&self, misspelling_index: usize) -> &Vec<String> {
@@ -277,9 +675,7 @@ impl Spellchecker {
     /// Adds `offset` to all of the misspellings' start and end positions, starting at and including the misspelling at index `from_idx`. The offset can be negative, in such case, the positions are moved "backwards" (subtracted from).
     pub fn offset_misspelling_positions(&mut self, offset: i32, from_idx: usize) {
         for misspelling in self.misspellings.iter_mut().skip(from_idx) {
-            // Shouldn't overflow, if it does, look for the error in caller
-            misspelling.start = (misspelling.start as i32 + offset) as usize;
-            misspelling.end = (misspelling.end as i32 + offset) as usize;
+            misspelling.offset(offset);
         }
     }
 }
@@ -289,7 +685,7 @@ mod tests {
     use super::*;
 
     fn get_spellchecker() -> Spellchecker {
-        Spellchecker::new().unwrap()
+        crate::test_support::bundled_spellchecker()
     }
 
     #[test]
@@ -409,7 +805,9 @@ mod tests {
                 word: "mispeled".to_string(),
                 start: 0,
                 end: 7,
-                suggestions: Vec::new()
+                kind: MisspellingKind::Spelling,
+                suggestions: Vec::new(),
+                in_source: false,
             }
         );
     }
@@ -427,7 +825,9 @@ mod tests {
                 word: "MiSpELed".to_string(),
                 start: 0,
                 end: 7,
-                suggestions: Vec::new()
+                kind: MisspellingKind::Spelling,
+                suggestions: Vec::new(),
+                in_source: false,
             }
         );
     }
@@ -438,7 +838,11 @@ mod tests {
 
         let spellchecker = get_spellchecker();
 
-        misspelling.suggest(&spellchecker.suggestion_dict);
+        misspelling.suggest(
+            &spellchecker.dicts,
+            &spellchecker.history,
+            &spellchecker.model,
+        );
         assert!(misspelling.get_suggestions().contains(&"this".to_string()));
         assert!(misspelling.get_suggestions().contains(&"the".to_string()));
         assert!(
@@ -456,7 +860,11 @@ mod tests {
         let spellchecker = get_spellchecker();
 
         let mut misspelling = Misspelling::new("comon".to_owned(), 0, 0);
-        misspelling.suggest(&spellchecker.suggestion_dict);
+        misspelling.suggest(
+            &spellchecker.dicts,
+            &spellchecker.history,
+            &spellchecker.model,
+        );
         assert!(
             misspelling
                 .get_suggestions()
@@ -467,7 +875,11 @@ mod tests {
         );
 
         let mut misspelling = Misspelling::new("womn".to_owned(), 0, 0);
-        misspelling.suggest(&spellchecker.suggestion_dict);
+        misspelling.suggest(
+            &spellchecker.dicts,
+            &spellchecker.history,
+            &spellchecker.model,
+        );
         assert!(
             misspelling
                 .get_suggestions()
@@ -480,7 +892,11 @@ mod tests {
         // https://en.wikipedia.org/wiki/Commonly_misspelled_English_words
 
         let mut misspelling = Misspelling::new("amatuer".to_owned(), 0, 0);
-        misspelling.suggest(&spellchecker.suggestion_dict);
+        misspelling.suggest(
+            &spellchecker.dicts,
+            &spellchecker.history,
+            &spellchecker.model,
+        );
         assert!(
             misspelling
                 .get_suggestions()
@@ -491,7 +907,11 @@ mod tests {
         );
 
         let mut misspelling = Misspelling::new("commited".to_owned(), 0, 0);
-        misspelling.suggest(&spellchecker.suggestion_dict);
+        misspelling.suggest(
+            &spellchecker.dicts,
+            &spellchecker.history,
+            &spellchecker.model,
+        );
         assert!(
             misspelling
                 .get_suggestions()
@@ -502,7 +922,11 @@ mod tests {
         );
 
         let mut misspelling = Misspelling::new("millenium".to_owned(), 0, 0);
-        misspelling.suggest(&spellchecker.suggestion_dict);
+        misspelling.suggest(
+            &spellchecker.dicts,
+            &spellchecker.history,
+            &spellchecker.model,
+        );
         assert!(
             misspelling
                 .get_suggestions()
@@ -513,7 +937,11 @@ mod tests {
         );
 
         let mut misspelling = Misspelling::new("nieghbor".to_owned(), 0, 0);
-        misspelling.suggest(&spellchecker.suggestion_dict);
+        misspelling.suggest(
+            &spellchecker.dicts,
+            &spellchecker.history,
+            &spellchecker.model,
+        );
         assert!(
             misspelling
                 .get_suggestions()
@@ -526,30 +954,259 @@ mod tests {
 
     #[test]
     fn test_suggestion_priority() {
-        let mut priorities = vec![
-            SuggestionPriority::new(1, i64::MAX),
-            SuggestionPriority::new(2, 10),
-            SuggestionPriority::new(2, 0),
-            SuggestionPriority::new(1, 10),
-            SuggestionPriority::new(0, 0),
+        let model = ErrorModel::default();
+        let priority = |suggestion, popularity| {
+            SuggestionPriority::new(&model, "helo", suggestion, popularity)
+        };
+        let mut priorities = [
+            ("help", priority("help", 1000)),
+            ("hello", priority("hello", 10)),
+            ("halo", priority("halo", 100)),
+            ("helot", priority("helot", 0)),
+            ("hell", priority("hell", 0).with_times_accepted(1)),
         ];
 
-        priorities.sort();
-        let mut iter = priorities.into_iter().rev();
+        priorities.sort_by(|a, b| b.1.cmp(&a.1));
+        let order: Vec<&str> = priorities.iter().map(|(word, _)| *word).collect();
+
+        // The accepted correction first, then by the number of edits and the popularity
+        assert_eq!(order, vec!["hell", "help", "halo", "hello", "helot"]);
+    }
+
+    #[test]
+    fn test_suggesting_accepted_corrections_first() {
+        let dict = Dictionary::from_sources(
+            "",
+            "tan 50
+teh 10
+ten 100
+the 20
+",
+        )
+        .unwrap();
+        let mut spellchecker = Spellchecker::with_dictionary(dict);
+        spellchecker.check("teg");
+        spellchecker.suggest(0);
+        assert_eq!(spellchecker.get_suggestions(0)[0], "ten");
+
+        spellchecker.set_history(CorrectionHistory::from_source("teg\tthe\nTeg\tthe\n"));
+        spellchecker.misspellings.clear();
+        spellchecker.check("Teg");
+        spellchecker.suggest(0);
+        assert_eq!(spellchecker.get_suggestions(0)[..2], ["the", "ten"]);
+    }
+
+    #[test]
+    fn test_suggestion_limits() {
+        let dict = Dictionary::from_sources("", "tan 50\nteh 10\nten 100\ntenth 1000\n").unwrap();
+        let mut spellchecker = Spellchecker::with_dictionary(dict);
+        spellchecker.set_suggestion_limits(SuggestionLimits {
+            count: 2,
+            max_distance: Some(1),
+        });
+        spellchecker.check("teg");
+        spellchecker.suggest(0);
+        assert_eq!(spellchecker.get_suggestions(0).len(), 2);
+        assert!(!spellchecker
+            .get_suggestions(0)
+            .contains(&"tenth".to_string()));
+
+        // Accepted corrections are suggested however far they are
+        spellchecker.set_history(CorrectionHistory::from_source("teg\ttenth\n"));
+        spellchecker.misspellings.clear();
+        spellchecker.check("teg");
+        spellchecker.suggest(0);
+        assert_eq!(spellchecker.get_suggestions(0)[0], "tenth");
+    }
+
+    #[test]
+    fn test_misspelling_position_multibyte() {
+        let mut spellchecker = get_spellchecker();
+
+        let text = "zażółć mispeled";
+        spellchecker.check(text);
+        let misspelling = spellchecker.misspellings().last().unwrap();
+        assert_eq!(misspelling.get_range(), (11, 18));
+        assert_eq!(&text[11..=18], "mispeled");
+    }
+
+    #[test]
+    fn test_wrongly_cased_words() {
+        let dict = Dictionary::from_sources("in\nlives\nLondon\nNASA\nshe\n", "").unwrap();
+        let mut spellchecker = Spellchecker::with_dictionary(dict);
+        spellchecker.check("she lives in london, NASA lives in LONDON, Nasa in Londn");
+
+        let misspellings: Vec<(&str, MisspellingKind)> = spellchecker
+            .misspellings()
+            .iter()
+            .map(|misspelling| (misspelling.get_word().as_str(), misspelling.get_kind()))
+            .collect();
+        assert_eq!(
+            misspellings,
+            vec![
+                ("london", MisspellingKind::Capitalization),
+                ("Nasa", MisspellingKind::Capitalization),
+                ("Londn", MisspellingKind::Spelling),
+            ]
+        );
+        assert_eq!(spellchecker.get_suggestions(0), &vec!["London".to_string()]);
+        assert_eq!(spellchecker.get_suggestions(1), &vec!["NASA".to_string()]);
+    }
+
+    #[test]
+    fn test_style_rules() {
+        let dict =
+            Dictionary::from_sources("an\ne-mail\nemail\nlogin\nsend\nthen\nto\n", "").unwrap();
+        let mut spellchecker = Spellchecker::with_dictionary(dict);
+        spellchecker.set_style_rules(
+            StyleRules::from_sources("AcmeSoft\n", "e-mail email\nlogin log in\n", "").unwrap(),
+        );
+        spellchecker.check("Send an E-mail to AcmeSoft, then login");
 
-        assert_eq!(iter.next(), Some(SuggestionPriority::new(0, 0)));
-        assert_eq!(iter.next(), Some(SuggestionPriority::new(1, i64::MAX)));
-        assert_eq!(iter.next(), Some(SuggestionPriority::new(1, 10)));
-        assert_eq!(iter.next(), Some(SuggestionPriority::new(2, 10)));
-        assert_eq!(iter.next(), Some(SuggestionPriority::new(2, 0)));
+        let misspellings: Vec<(&str, MisspellingKind)> = spellchecker
+            .misspellings()
+            .iter()
+            .map(|misspelling| (misspelling.get_word().as_str(), misspelling.get_kind()))
+            .collect();
+        assert_eq!(
+            misspellings,
+            vec![
+                ("E-mail", MisspellingKind::Replacement),
+                ("AcmeSoft", MisspellingKind::Forbidden),
+                ("login", MisspellingKind::Replacement),
+            ]
+        );
+        assert_eq!(spellchecker.get_suggestions(0), &vec!["email".to_string()]);
+        assert_eq!(spellchecker.get_suggestions(2), &vec!["log in".to_string()]);
+    }
+
+    #[test]
+    fn test_phrases() {
+        let dict = Dictionary::from_sources("ad\nand\nin\nlives\nnew\nshe\nyork\n", "").unwrap();
+        let mut spellchecker = Spellchecker::with_dictionary(dict);
+        spellchecker.set_phrases(PhraseList::from_source("New York\nad hoc\n"));
+        spellchecker.check("she lives in New York, ad hoc, in new york and in ad hok");
+
+        let misspellings: Vec<(&str, (usize, usize), MisspellingKind)> = spellchecker
+            .misspellings()
+            .iter()
+            .map(|misspelling| {
+                (
+                    misspelling.get_word().as_str(),
+                    misspelling.get_range(),
+                    misspelling.get_kind(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            misspellings,
+            vec![
+                ("new york", (34, 41), MisspellingKind::Capitalization),
+                ("ad hok", (50, 55), MisspellingKind::Spelling),
+            ]
+        );
+        assert_eq!(
+            spellchecker.get_suggestions(0),
+            &vec!["New York".to_string()]
+        );
+        assert_eq!(spellchecker.get_suggestions(1), &vec!["ad hoc".to_string()]);
     }
 
     #[test]
-    fn test_word_separate_test() {
-        assert!(Spellchecker::separates_word(&'.'));
-        assert!(Spellchecker::separates_word(&'&'));
-        assert!(!Spellchecker::separates_word(&'"'));
-        assert!(!Spellchecker::separates_word(&'\''));
+    fn test_adding_words() {
+        let dict = Dictionary::from_sources("and\nin\nlives\nshe\n", "").unwrap();
+        let mut spellchecker = Spellchecker::with_dictionary(dict);
+        spellchecker.check("she lives in Zurich, zurich and Zurch");
+        assert_eq!(spellchecker.misspellings().len(), 3);
+
+        spellchecker.add_word(LayerKind::Session, "zurich").unwrap();
+        let words: Vec<&str> = spellchecker
+            .misspellings()
+            .iter()
+            .map(|misspelling| misspelling.get_word().as_str())
+            .collect();
+        assert_eq!(words, vec!["Zurch"]);
+        assert_eq!(
+            spellchecker.dictionaries().lookup("Zurich").1,
+            Some(LayerKind::Session)
+        );
+    }
+
+    #[test]
+    fn test_hyphenated_words() {
+        let dict = Dictionary::from_sources("cat\nmother-in-law\npost\nwar\n", "").unwrap();
+        let mut spellchecker = Spellchecker::with_dictionary(dict);
+        spellchecker.check("mother-in-law post-war post-wor cat-");
+
+        let misspellings: Vec<(&str, (usize, usize))> = spellchecker
+            .misspellings()
+            .iter()
+            .map(|misspelling| (misspelling.get_word().as_str(), misspelling.get_range()))
+            .collect();
+        assert_eq!(misspellings, vec![("wor", (28, 30))]);
+    }
+
+    #[test]
+    fn test_suggestions_in_canonical_case() {
+        let dict = Dictionary::from_sources("NASA\nnose\n", "nasa\t10\nnose\t5\n").unwrap();
+        let mut misspelling = Misspelling::new(String::from("nasaa"), 0, 4);
+        assert_eq!(
+            misspelling.suggest(
+                &DictionaryStack::new(dict),
+                &CorrectionHistory::default(),
+                &ErrorModel::default()
+            )[0],
+            "NASA"
+        );
+    }
+
+    #[test]
+    fn test_checking_po_file() {
+        let mut spellchecker = get_spellchecker();
+
+        let text = "#, fuzy\nmsgid \"Wrld %s\"\nmsgstr \"\"\n\"Hello %s, \"\n\"welcom\\n\"\n";
+        spellchecker.check_with(text, &po::PoFile { check_msgid: false });
+        assert_eq!(spellchecker.misspellings().len(), 1);
+        let misspelling = &spellchecker.misspellings()[0];
+        assert_eq!(misspelling.get_word(), "welcom");
+        let (start, end) = misspelling.get_range();
+        assert_eq!(&text[start..=end], "welcom");
+        spellchecker.misspellings.clear();
+
+        spellchecker.check_with(text, &po::PoFile { check_msgid: true });
+        assert_eq!(spellchecker.misspellings().len(), 2);
+        assert_eq!(spellchecker.misspellings()[0].get_word(), "Wrld");
+    }
+
+    #[test]
+    fn test_source_language() {
+        let french = Dictionary::from_sources("bonjour\nle\nmonde\n", "monde 10\n").unwrap();
+        let english = Dictionary::from_sources("hello\nworld\n", "world 10\n").unwrap();
+        let mut spellchecker = Spellchecker::with_dictionary(french);
+        spellchecker.set_source_language(Spellchecker::with_dictionary(english));
+
+        // The source strings are checked in English, and the translations in French
+        let text = "msgid \"Hello wrld\"\nmsgstr \"Bonjour le mond\"\n";
+        spellchecker.check_with(text, &po::PoFile { check_msgid: true });
+        let found: Vec<(&str, bool)> = spellchecker
+            .misspellings()
+            .iter()
+            .map(|misspelling| (misspelling.get_word().as_str(), misspelling.in_source))
+            .collect();
+        assert_eq!(found, vec![("wrld", true), ("mond", false)]);
+
+        // And so are their suggestions
+        spellchecker.suggest(0);
+        spellchecker.suggest(1);
+        assert_eq!(spellchecker.get_suggestions(0), &vec!["world".to_string()]);
+        assert_eq!(spellchecker.get_suggestions(1), &vec!["monde".to_string()]);
+
+        // Words are added to both languages
+        spellchecker.add_word(LayerKind::Session, "wrld").unwrap();
+        assert_eq!(spellchecker.misspellings().len(), 1);
+        spellchecker.misspellings.clear();
+        spellchecker.check_with(text, &po::PoFile { check_msgid: true });
+        assert_eq!(spellchecker.misspellings()[0].get_word(), "mond");
     }
 
     // Suggestion index
@@ -592,7 +1249,9 @@ mod tests {
             word: "".to_string(),
             start,
             end,
+            kind: MisspellingKind::Spelling,
             suggestions: Vec::new(),
+            in_source: false,
         }
     }
 
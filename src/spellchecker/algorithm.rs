@@ -1,7 +1,38 @@
 use std::cmp::min;
 
-pub fn search_for_word(word: &str, dict: &[String]) -> Option<usize> {
-    if word.contains(' ') || word.is_empty() {
+/// A list of words sorted in byte order, which can be binary searched.
+pub trait WordList {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get_word(&self, index: usize) -> &str;
+}
+
+impl WordList for [String] {
+    fn len(&self) -> usize {
+        <[String]>::len(self)
+    }
+
+    fn get_word(&self, index: usize) -> &str {
+        &self[index]
+    }
+}
+
+impl WordList for Vec<String> {
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn get_word(&self, index: usize) -> &str {
+        &self[index]
+    }
+}
+
+pub fn search_for_word<D: WordList + ?Sized>(word: &str, dict: &D) -> Option<usize> {
+    if word.contains(' ') || word.is_empty() || dict.is_empty() {
         return None;
     }
 
@@ -11,11 +42,11 @@ pub fn search_for_word(word: &str, dict: &[String]) -> Option<usize> {
     let mut right: usize = dict.len() - 1;
     let mut middle: usize;
 
-    let word = &word.to_string();
+    let word = word.as_str();
 
     while left != right {
-        middle = (left + right + 1) / 2;
-        let middle_word = dict.get(middle).unwrap();
+        middle = (left + right).div_ceil(2);
+        let middle_word = dict.get_word(middle);
 
         if middle_word > word {
             right = middle - 1;
@@ -24,16 +55,16 @@ pub fn search_for_word(word: &str, dict: &[String]) -> Option<usize> {
         }
     }
 
-    if dict.get(left).unwrap() == word {
+    if dict.get_word(left) == word {
         Some(left)
-    } else if dict.get(right).unwrap() == word {
+    } else if dict.get_word(right) == word {
         Some(right)
     } else {
         None
     }
 }
 
-pub fn is_word_correct(word: &str, dict: &[String]) -> bool {
+pub fn is_word_correct<D: WordList + ?Sized>(word: &str, dict: &D) -> bool {
     if word.contains(' ') || word.is_empty() {
         return false;
     }
@@ -49,8 +80,8 @@ pub fn edit_distance(source: &str, target: &str) -> i32 {
     let target: Vec<char> = target.chars().collect();
 
     // source of "" can be made into every substring of target by insertion of another letter
-    for i in 0..=target.len() {
-        dp[0][i] = i as i32;
+    for (i, cell) in dp[0].iter_mut().enumerate() {
+        *cell = i as i32;
     }
 
     // target of "" can be be made by removing an appropriately larger and larger number of letters
@@ -80,6 +111,40 @@ pub fn edit_distance(source: &str, target: &str) -> i32 {
     dp[source.len()][target.len()]
 }
 
+/// The edit distance counting a swap of two adjacent letters as a single edit, which is the most
+/// common kind of typo.
+pub fn transposition_distance(source: &str, target: &str) -> usize {
+    let source: Vec<char> = source.chars().collect();
+    let target: Vec<char> = target.chars().collect();
+    transposition_distances(&source, &target)[source.len()][target.len()]
+}
+
+/// Returns the table of the distances between all of the prefixes of `source` and `target`, where
+/// the edits are inserting, deleting or substituting a letter, or swapping two adjacent letters.
+pub fn transposition_distances(source: &[char], target: &[char]) -> Vec<Vec<usize>> {
+    let (n, m) = (source.len(), target.len());
+    let mut distances = vec![vec![0usize; m + 1]; n + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in distances[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=n {
+        for j in 1..=m {
+            let substitution = usize::from(source[i - 1] != target[j - 1]);
+            let mut distance = (distances[i - 1][j - 1] + substitution)
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && source[i - 1] == target[j - 2] && source[i - 2] == target[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -98,6 +163,12 @@ mod tests {
         assert_eq!(search_for_word("blue", &dict), Some(3));
     }
 
+    #[test]
+    fn test_searching_empty_dict() {
+        let dict: Vec<String> = Vec::new();
+        assert_eq!(search_for_word("apple", &dict), None);
+    }
+
     #[test]
     fn test_finding_nonexistant_word() {
         let dict = create_dict();
@@ -182,4 +253,12 @@ mod tests {
         assert_eq!(edit_distance("hello", "kelm"), 3);
         assert_eq!(edit_distance("sittmg", "setting"), 3);
     }
+
+    #[test]
+    fn test_transposition_distance() {
+        assert_eq!(transposition_distance("teh", "the"), 1);
+        assert_eq!(transposition_distance("kitten", "smitten"), 2);
+        assert_eq!(transposition_distance("żółw", "żwół"), 2);
+        assert_eq!(transposition_distance("", "abc"), 3);
+    }
 }
@@ -0,0 +1,333 @@
+//! The noisy channel model used to rank suggestions.
+//!
+//! A misspelling is seen as the intended word sent through a noisy channel - the typing - which
+//! substitutes, inserts, deletes and transposes characters. The suggestions are ranked by how
+//! likely each of them is to be the intended word: the probability of the word itself, given by
+//! its popularity, times the probability of the typing turning it into the misspelling.
+//!
+//! The probabilities of the single character edits are estimated from a corpus of
+//! misspelling→correction pairs (`corrections.txt`, a list of common English misspellings bundled
+//! with the program, and the user's correction history), following Kernighan, Church and Gale
+//! (1990): each edit is counted, and divided by the number of times the characters it applies to
+//! occur in the corrections. Edits the corpus doesn't have are smoothed to a small probability, so
+//! that with no corpus at all the model ranks the suggestions by the number of edits, and then by
+//! popularity.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+
+use crate::prelude::*;
+use crate::spellchecker::algorithm::transposition_distances;
+use crate::spellchecker::embedded;
+
+/// Marks the start of a word, so that edits of the first character have a preceding character
+const WORD_START: char = '^';
+/// The number of characters an edit can produce, used to smooth the edit probabilities
+const ALPHABET_SIZE: f64 = 26.0;
+/// The probability of making a typo at a given character
+const ERROR_RATE: f64 = 0.001;
+
+/// The counts of the single character edits, keyed by the characters they apply to.
+#[derive(Debug, Default, Clone)]
+struct EditCounts {
+    substitutions: HashMap<(char, char), u32>, // (typed, intended)
+    insertions: HashMap<(char, char), u32>,    // (intended character before, typed)
+    deletions: HashMap<(char, char), u32>,     // (intended character before, intended)
+    transpositions: HashMap<(char, char), u32>, // (intended, intended after), typed swapped
+}
+
+/// A single character edit turning the intended word into the typed one.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Edit {
+    Substitution { typed: char, intended: char },
+    Insertion { before: char, typed: char },
+    Deletion { before: char, intended: char },
+    Transposition { first: char, second: char },
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ErrorModel {
+    edits: EditCounts,
+    unigrams: HashMap<char, u32>, // How many times the characters occur in the corrections
+    bigrams: HashMap<(char, char), u32>, // How many times the pairs of characters occur in them
+}
+
+impl ErrorModel {
+    /// Trains the model on the corpus at `path`, holding a misspelling and its correction
+    /// separated by a tab on every line. Without a path, the corpus embedded in the binary is used,
+    /// and if there is none the model is left untrained.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let corpus = match path {
+            Some(path) => fs::read_to_string(path)
+                .with_context(|| format!("couldn't read {}", path.display()))?,
+            None => match embedded::read(embedded::List::Corrections) {
+                Some(contents) => String::from_utf8(contents?)
+                    .context("the embedded corrections corpus isn't valid UTF-8")?,
+                None => String::new(),
+            },
+        };
+
+        let mut model = ErrorModel::default();
+        for (misspelling, correction) in corpus.lines().filter_map(|line| line.split_once('\t')) {
+            model.learn(misspelling.trim(), correction.trim());
+        }
+        Ok(model)
+    }
+
+    /// Counts the edits which turned `correction` into `misspelling`
+    pub fn learn(&mut self, misspelling: &str, correction: &str) {
+        let typed: Vec<char> = misspelling.to_lowercase().chars().collect();
+        let intended: Vec<char> = correction.to_lowercase().chars().collect();
+        if typed.is_empty() || intended.is_empty() || typed == intended {
+            return;
+        }
+
+        for edit in align(&typed, &intended) {
+            let (counts, key) = match edit {
+                Edit::Substitution { typed, intended } => {
+                    (&mut self.edits.substitutions, (typed, intended))
+                }
+                Edit::Insertion { before, typed } => (&mut self.edits.insertions, (before, typed)),
+                Edit::Deletion { before, intended } => {
+                    (&mut self.edits.deletions, (before, intended))
+                }
+                Edit::Transposition { first, second } => {
+                    (&mut self.edits.transpositions, (first, second))
+                }
+            };
+            *counts.entry(key).or_default() += 1;
+        }
+
+        let mut before = WORD_START;
+        *self.unigrams.entry(WORD_START).or_default() += 1;
+        for &c in &intended {
+            *self.unigrams.entry(c).or_default() += 1;
+            *self.bigrams.entry((before, c)).or_default() += 1;
+            before = c;
+        }
+    }
+
+    /// The cost of the edit, its negative log probability
+    fn cost(&self, edit: Edit) -> f64 {
+        let unigram = |c: char| self.unigrams.get(&c).copied().unwrap_or(0);
+        let bigram = |a: char, b: char| self.bigrams.get(&(a, b)).copied().unwrap_or(0);
+
+        let (count, total) = match edit {
+            Edit::Substitution { typed, intended } => (
+                self.edits.substitutions.get(&(typed, intended)),
+                unigram(intended),
+            ),
+            Edit::Insertion { before, typed } => {
+                (self.edits.insertions.get(&(before, typed)), unigram(before))
+            }
+            Edit::Deletion { before, intended } => (
+                self.edits.deletions.get(&(before, intended)),
+                bigram(before, intended),
+            ),
+            Edit::Transposition { first, second } => (
+                self.edits.transpositions.get(&(first, second)),
+                bigram(first, second),
+            ),
+        };
+        let count = count.copied().unwrap_or(0);
+
+        let probability = (count as f64 + 1.0) / (total.max(count) as f64 + ALPHABET_SIZE);
+        -(ERROR_RATE * probability).ln()
+    }
+
+    /// The log probability of typing `misspelling` when meaning `word`, along the most likely
+    /// sequence of edits. Both are expected to be lowercase.
+    pub fn log_likelihood(&self, misspelling: &str, word: &str) -> f64 {
+        let typed: Vec<char> = misspelling.chars().collect();
+        let intended: Vec<char> = word.chars().collect();
+        let before = |j: usize| match j {
+            0 => WORD_START,
+            _ => intended[j - 1],
+        };
+
+        // costs[i][j] is the cost of typing the first i characters when meaning the first j
+        let mut costs = vec![vec![f64::INFINITY; intended.len() + 1]; typed.len() + 1];
+        costs[0][0] = 0.0;
+        for i in 0..=typed.len() {
+            for j in 0..=intended.len() {
+                let mut cost = costs[i][j];
+                if i > 0 && j > 0 {
+                    let substitution = match typed[i - 1] == intended[j - 1] {
+                        true => 0.0,
+                        false => self.cost(Edit::Substitution {
+                            typed: typed[i - 1],
+                            intended: intended[j - 1],
+                        }),
+                    };
+                    cost = cost.min(costs[i - 1][j - 1] + substitution);
+                }
+                if i > 0 {
+                    let insertion = self.cost(Edit::Insertion {
+                        before: before(j),
+                        typed: typed[i - 1],
+                    });
+                    cost = cost.min(costs[i - 1][j] + insertion);
+                }
+                if j > 0 {
+                    let deletion = self.cost(Edit::Deletion {
+                        before: before(j - 1),
+                        intended: intended[j - 1],
+                    });
+                    cost = cost.min(costs[i][j - 1] + deletion);
+                }
+                if i > 1
+                    && j > 1
+                    && typed[i - 1] == intended[j - 2]
+                    && typed[i - 2] == intended[j - 1]
+                    && typed[i - 1] != typed[i - 2]
+                {
+                    let transposition = self.cost(Edit::Transposition {
+                        first: intended[j - 2],
+                        second: intended[j - 1],
+                    });
+                    cost = cost.min(costs[i - 2][j - 2] + transposition);
+                }
+                costs[i][j] = cost;
+            }
+        }
+
+        -costs[typed.len()][intended.len()]
+    }
+}
+
+/// Finds the fewest edits turning the intended word into the typed one.
+fn align(typed: &[char], intended: &[char]) -> Vec<Edit> {
+    let (n, m) = (typed.len(), intended.len());
+    let distances = transposition_distances(typed, intended);
+
+    // Walk back from the end, along the edits the distances were computed from
+    let before = |j: usize| match j {
+        0 => WORD_START,
+        _ => intended[j - 1],
+    };
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        let distance = distances[i][j];
+        if i > 0 && j > 0 && typed[i - 1] == intended[j - 1] && distance == distances[i - 1][j - 1]
+        {
+            (i, j) = (i - 1, j - 1);
+        } else if i > 1
+            && j > 1
+            && typed[i - 1] == intended[j - 2]
+            && typed[i - 2] == intended[j - 1]
+            && distance == distances[i - 2][j - 2] + 1
+        {
+            edits.push(Edit::Transposition {
+                first: intended[j - 2],
+                second: intended[j - 1],
+            });
+            (i, j) = (i - 2, j - 2);
+        } else if i > 0 && j > 0 && distance == distances[i - 1][j - 1] + 1 {
+            edits.push(Edit::Substitution {
+                typed: typed[i - 1],
+                intended: intended[j - 1],
+            });
+            (i, j) = (i - 1, j - 1);
+        } else if i > 0 && distance == distances[i - 1][j] + 1 {
+            edits.push(Edit::Insertion {
+                before: before(j),
+                typed: typed[i - 1],
+            });
+            i -= 1;
+        } else {
+            edits.push(Edit::Deletion {
+                before: before(j - 1),
+                intended: intended[j - 1],
+            });
+            j -= 1;
+        }
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(word: &str) -> Vec<char> {
+        word.chars().collect()
+    }
+
+    #[test]
+    fn test_aligning_words() {
+        assert_eq!(
+            align(&chars("teh"), &chars("the")),
+            vec![Edit::Transposition {
+                first: 'h',
+                second: 'e'
+            }]
+        );
+        assert_eq!(
+            align(&chars("recieve"), &chars("receive")),
+            vec![Edit::Transposition {
+                first: 'e',
+                second: 'i'
+            }]
+        );
+        assert_eq!(
+            align(&chars("aple"), &chars("apple")),
+            vec![Edit::Deletion {
+                before: 'a',
+                intended: 'p'
+            }]
+        );
+        assert_eq!(
+            align(&chars("seperate"), &chars("separate")),
+            vec![Edit::Substitution {
+                typed: 'e',
+                intended: 'a'
+            }]
+        );
+        assert_eq!(
+            align(&chars("xhello"), &chars("hello")),
+            vec![Edit::Insertion {
+                before: WORD_START,
+                typed: 'x'
+            }]
+        );
+    }
+
+    #[test]
+    fn test_untrained_model_counts_edits() {
+        let model = ErrorModel::default();
+        let one_edit = model.log_likelihood("helo", "hello");
+        let two_edits = model.log_likelihood("helo", "hole");
+        assert!(one_edit > two_edits);
+        assert_eq!(model.log_likelihood("hello", "hello"), 0.0);
+        assert_eq!(
+            model.log_likelihood("teh", "the"),
+            model.log_likelihood("tha", "the")
+        );
+    }
+
+    #[test]
+    fn test_learning_common_errors() {
+        let mut model = ErrorModel::default();
+        for (misspelling, correction) in [
+            ("seperate", "separate"),
+            ("desparate", "desperate"),
+            ("grammer", "grammar"),
+        ] {
+            model.learn(misspelling, correction);
+        }
+
+        // Confusing 'e' and 'a' is now more likely than other substitutions
+        assert!(
+            model.log_likelihood("sentance", "sentence")
+                > model.log_likelihood("sentonce", "sentence")
+        );
+        assert!(
+            model.log_likelihood("calender", "calendar")
+                > model.log_likelihood("calendor", "calendar")
+        );
+    }
+}
@@ -0,0 +1,816 @@
+//! The word lists used by the spellchecker, kept in a compiled binary form.
+//!
+//! Reading and splitting the text word lists on every start is slow, so the first time they are
+//! loaded they are compiled into a single binary file, which is then memory-mapped on later runs.
+//! The compiled file starts with a header holding a format version and the stamps of the source
+//! lists it was compiled from, and it is rebuilt whenever either of those doesn't match. The stamp
+//! of a file is its size and modification time, so the sources don't have to be read to find out
+//! that the compiled file is up to date. The stamp of a list embedded in the binary is its size
+//! and the hash of its contents, computed when the program was built.
+//!
+//! Layout of the compiled file (all numbers are little endian):
+//! ```text
+//! magic            8 bytes, "SPELCHEK"
+//! version          u32
+//! stamps           u64 size and u64 modification time (or hash) of each of the source lists
+//! words            string table, lowercased and sorted
+//! forms            string table, the canonical casings of each word
+//! suggestions      string table
+//! popularities     i64 for each suggestion
+//! ```
+//! where a string table is the number of strings (u32), followed by `count + 1` offsets (u32)
+//! into the bytes of the concatenated strings that follow them. The strings are only checked to be
+//! valid UTF-8 when they are read, so that mapping the file doesn't have to go through all of it.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::ops::{Deref, Range};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{anyhow, Context};
+use memmap2::Mmap;
+
+use super::algorithm::{search_for_word, WordList};
+use super::embedded;
+use crate::paths::{DictionaryPaths, DICT_FILE_NAME, SUGGESTION_DICT_FILE_NAME};
+use crate::prelude::*;
+
+const MAGIC: &[u8; 8] = b"SPELCHEK";
+
+/// The version of the compiled format. Must be increased whenever the layout or the way the
+/// source lists are compiled changes, so that old compiled files get rebuilt.
+const FORMAT_VERSION: u32 = 4;
+
+const HEADER_LEN: usize = MAGIC.len() + 4 + 2 * 16;
+
+/// What a source list is recognised by, to tell whether a compiled dictionary is up to date.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Stamp {
+    size: u64,
+    modified: u64, // Nanoseconds since the Unix epoch, or the hash of an embedded list
+}
+
+impl Stamp {
+    /// Returns the stamp of the source list at `path`, or of the embedded copy of `list` if there
+    /// is no path
+    fn of(path: Option<&Path>, list: embedded::List) -> Result<Self> {
+        let Some(path) = path else {
+            return match embedded::identity(list) {
+                Some((size, hash)) => Ok(Stamp {
+                    size,
+                    modified: hash,
+                }),
+                None => Err(missing_dictionary()),
+            };
+        };
+
+        let metadata = fs::metadata(path)
+            .with_context(|| format!("couldn't read the dictionary at {}", path.display()))?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| since.as_nanos() as u64);
+        Ok(Stamp {
+            size: metadata.len(),
+            modified,
+        })
+    }
+}
+
+/// The bytes of a compiled dictionary, either built in memory or mapped from a file.
+#[derive(Debug)]
+enum Bytes {
+    Owned(Vec<u8>),
+    Mapped(Mmap),
+}
+
+impl Deref for Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Bytes::Owned(bytes) => bytes,
+            Bytes::Mapped(mmap) => mmap,
+        }
+    }
+}
+
+/// The position of a string table inside of the compiled bytes.
+#[derive(Debug, Default, Clone, Copy)]
+struct Table {
+    count: usize,
+    offsets: usize, // The position of the first offset
+    strings: usize, // The position of the first byte of the first string
+}
+
+impl Table {
+    /// Reads the table starting at `pos`, returning it and the position right after it.
+    fn read(bytes: &[u8], pos: usize) -> Result<(Table, usize)> {
+        let count = read_u32(bytes, pos)? as usize;
+        let offsets = pos + 4;
+        let strings = offsets + (count + 1) * 4;
+        let strings_len = read_u32(bytes, offsets + count * 4)? as usize;
+        let end = strings + strings_len;
+
+        if end > bytes.len() {
+            return Err(anyhow!("compiled dictionary is truncated"));
+        }
+
+        Ok((
+            Table {
+                count,
+                offsets,
+                strings,
+            },
+            end,
+        ))
+    }
+
+    /// Returns the string at `index`, or an empty string if it isn't valid UTF-8, which only a
+    /// corrupted file can hold
+    fn get<'a>(&self, bytes: &'a [u8], index: usize) -> &'a str {
+        let offset = |i: usize| read_u32(bytes, self.offsets + i * 4).unwrap_or(0) as usize;
+        let range = self.strings + offset(index)..self.strings + offset(index + 1);
+        bytes
+            .get(range)
+            .and_then(|string| std::str::from_utf8(string).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// The result of looking a word up in the dictionary.
+#[derive(Debug, PartialEq)]
+pub enum WordStatus {
+    Correct,
+    /// The word is known, but has to be written in one of the listed casings
+    WrongCase(Vec<String>),
+    Unknown,
+}
+
+/// The list of correct words and the list of words used for suggestions together with their
+/// popularity.
+#[derive(Debug)]
+pub struct Dictionary {
+    bytes: Bytes,
+    words: Table,
+    forms: Table,
+    suggestions: Table,
+    popularities: usize, // The position of the first popularity
+}
+
+impl Default for Dictionary {
+    fn default() -> Self {
+        Dictionary::from_sources("", "").expect("empty sources always compile")
+    }
+}
+
+impl WordList for Dictionary {
+    fn len(&self) -> usize {
+        self.words.count
+    }
+
+    fn get_word(&self, index: usize) -> &str {
+        self.words.get(&self.bytes, index)
+    }
+}
+
+impl Dictionary {
+    /// Loads the dictionary compiled from the word list and the suggestion list found at
+    /// `paths`. The compiled dictionary is memory-mapped from `paths.compiled` if it is up to
+    /// date, otherwise it is compiled again and written there.
+    ///
+    /// If either of the lists wasn't found, the one embedded in the binary is used in its place
+    /// (if the program was built with the `embedded-dict` feature).
+    pub fn load(paths: &DictionaryPaths) -> Result<Self> {
+        // The sources are stamped before they are read, so that a change made while compiling
+        // them causes another rebuild rather than being missed
+        let stamps = [
+            Stamp::of(paths.dict.as_deref(), embedded::List::Dict)?,
+            Stamp::of(
+                paths.suggestion_dict.as_deref(),
+                embedded::List::SuggestionDict,
+            )?,
+        ];
+        if let Some(dictionary) = paths
+            .compiled
+            .as_deref()
+            .and_then(|path| Dictionary::open_compiled(path, stamps))
+        {
+            return Ok(dictionary);
+        }
+
+        let dict_source = read_source(paths.dict.as_deref(), embedded::List::Dict)?;
+        let suggestion_source = read_source(
+            paths.suggestion_dict.as_deref(),
+            embedded::List::SuggestionDict,
+        )?;
+
+        let dict_name = source_name(paths.dict.as_deref(), DICT_FILE_NAME);
+        let suggestion_name =
+            source_name(paths.suggestion_dict.as_deref(), SUGGESTION_DICT_FILE_NAME);
+        let bytes = compile(
+            Source {
+                name: &dict_name,
+                text: &String::from_utf8_lossy(&dict_source),
+            },
+            Source {
+                name: &suggestion_name,
+                text: &String::from_utf8_lossy(&suggestion_source),
+            },
+            stamps,
+        )?;
+
+        // Not being able to store the compiled dictionary only makes the next start slower
+        if let Some(path) = &paths.compiled {
+            let _ = write_atomically(path, &bytes);
+        }
+
+        Dictionary::from_bytes(Bytes::Owned(bytes))
+    }
+
+    /// Compiles the dictionary in memory from the contents of the word list and the suggestion
+    /// list.
+    pub fn from_sources(dict_source: &str, suggestion_source: &str) -> Result<Self> {
+        let bytes = compile(
+            Source {
+                name: DICT_FILE_NAME,
+                text: dict_source,
+            },
+            Source {
+                name: SUGGESTION_DICT_FILE_NAME,
+                text: suggestion_source,
+            },
+            [Stamp::default(); 2],
+        )?;
+        Dictionary::from_bytes(Bytes::Owned(bytes))
+    }
+
+    /// Maps the compiled dictionary at `path`, if it exists and was compiled by this version of
+    /// the program from sources with the passed stamps.
+    fn open_compiled(path: &Path, stamps: [Stamp; 2]) -> Option<Self> {
+        let file = File::open(path).ok()?;
+
+        // SAFETY: compiled dictionaries are only ever written to a temporary file which is then
+        // renamed over the old one, so the mapped file is never modified while in use.
+        let mmap = unsafe { Mmap::map(&file) }.ok()?;
+
+        if read_header(&mmap).ok()? != (FORMAT_VERSION, stamps) {
+            return None;
+        }
+        Dictionary::from_bytes(Bytes::Mapped(mmap)).ok()
+    }
+
+    fn from_bytes(bytes: Bytes) -> Result<Self> {
+        let (version, _) = read_header(&bytes)?;
+        if version != FORMAT_VERSION {
+            return Err(anyhow!("unsupported compiled dictionary version {version}"));
+        }
+
+        let (words, pos) = Table::read(&bytes, HEADER_LEN)?;
+        let (forms, pos) = Table::read(&bytes, pos)?;
+        let (suggestions, popularities) = Table::read(&bytes, pos)?;
+        if forms.count != words.count {
+            return Err(anyhow!("compiled dictionary is corrupted"));
+        }
+        if popularities + suggestions.count * 8 > bytes.len() {
+            return Err(anyhow!("compiled dictionary is truncated"));
+        }
+
+        Ok(Dictionary {
+            bytes,
+            words,
+            forms,
+            suggestions,
+            popularities,
+        })
+    }
+
+    /// Looks the word up, taking the casings that proper nouns and acronyms must be written in
+    /// into account.
+    pub fn lookup(&self, word: &str) -> WordStatus {
+        let index = match search_for_word(word, self) {
+            Some(index) => index,
+            None => return WordStatus::Unknown,
+        };
+
+        let forms = self.forms.get(&self.bytes, index);
+        if forms.is_empty() || forms.split(' ').any(|form| matches_casing(word, form)) {
+            return WordStatus::Correct;
+        }
+        WordStatus::WrongCase(forms.split(' ').map(str::to_string).collect())
+    }
+
+    /// Returns the word in its canonical casing if it has one, otherwise the word unchanged
+    pub fn canonical_form<'a>(&'a self, word: &'a str) -> &'a str {
+        match search_for_word(word, self) {
+            Some(index) => match self.forms.get(&self.bytes, index).split(' ').next() {
+                Some(form) if !form.is_empty() => form,
+                _ => word,
+            },
+            None => word,
+        }
+    }
+
+    /// Returns the words used for suggestions together with their popularity
+    pub fn suggestions(&self) -> impl Iterator<Item = (&str, i64)> + '_ {
+        (0..self.suggestions.count).map(|i| {
+            let pos = self.popularities + i * 8;
+            let popularity = i64::from_le_bytes(self.bytes[pos..pos + 8].try_into().unwrap());
+            (self.suggestions.get(&self.bytes, i), popularity)
+        })
+    }
+
+    /// Returns the popularity of the word, if it is one of the words used for suggestions
+    pub fn popularity(&self, word: &str) -> Option<i64> {
+        let word = word.to_lowercase();
+        self.suggestions()
+            .find(|(suggestion, _)| *suggestion == word)
+            .map(|(_, popularity)| popularity)
+    }
+
+    /// Whether the dictionary is memory-mapped from a compiled file
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.bytes, Bytes::Mapped(_))
+    }
+}
+
+/// Whether `word` is written in the casing `form`, or in all capitals, as in headings.
+fn matches_casing(word: &str, form: &str) -> bool {
+    if word == form {
+        return true;
+    }
+    let is_shouted = word.chars().count() > 1 && !word.chars().any(char::is_lowercase);
+    is_shouted && word == form.to_uppercase()
+}
+
+/// Reads the source word list at `path`, or the embedded copy of `list` if there is no path.
+fn read_source(path: Option<&Path>, list: embedded::List) -> Result<Vec<u8>> {
+    match path {
+        Some(path) => fs::read(path)
+            .with_context(|| format!("couldn't read the dictionary at {}", path.display())),
+        None => embedded::read(list).unwrap_or_else(|| Err(missing_dictionary())),
+    }
+}
+
+fn missing_dictionary() -> anyhow::Error {
+    anyhow!("couldn't find a dictionary (it can be installed with `make dict`)")
+}
+
+/// The name errors in a source list are reported under: its path, or the name of the embedded
+/// file.
+fn source_name(path: Option<&Path>, file_name: &str) -> String {
+    match path {
+        Some(path) => path.display().to_string(),
+        None => format!("{file_name} (embedded)"),
+    }
+}
+
+/// A source word list together with a name to report its errors under.
+#[derive(Debug, Clone, Copy)]
+struct Source<'a> {
+    name: &'a str,
+    text: &'a str,
+}
+
+/// A word of the word list, with the casings it has to be written in.
+#[derive(Debug, PartialEq)]
+struct Entry<'a> {
+    word: Cow<'a, str>, // Lowercased
+    forms: String,      // The canonical casings separated by spaces, empty if any casing is correct
+}
+
+/// Parses the word list, which holds one word per line. The words are lowercased, sorted by
+/// bytes and deduplicated, since that is what [`search_for_word`](super::algorithm::search_for_word)
+/// relies on, so the file itself doesn't need to be.
+///
+/// Words listed with capital letters (like "London", "NASA" or "iPhone") are only correct in that
+/// casing, unless the word is also listed in lowercase.
+fn parse_words(source: Source) -> Result<Vec<Entry>> {
+    let mut words: Vec<(Cow<str>, &str)> = Vec::new(); // The lowercased and the original word
+
+    for (i, line) in source.text.lines().enumerate() {
+        let word = line.trim();
+        if word.is_empty() {
+            continue;
+        }
+        if word.contains(char::is_whitespace) {
+            return Err(anyhow!(
+                "{}:{}: '{word}' contains whitespace, expected one word per line",
+                source.name,
+                i + 1
+            ));
+        }
+
+        match word.chars().any(char::is_uppercase) {
+            true => words.push((Cow::Owned(word.to_lowercase()), word)),
+            false => words.push((Cow::Borrowed(word), word)),
+        }
+    }
+
+    words.sort_unstable();
+    words.dedup();
+
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut case_insensitive = false; // Whether the last entry was listed in lowercase
+    for (word, form) in words {
+        match entries.last_mut() {
+            Some(entry) if entry.word == word => {
+                if case_insensitive {
+                    continue;
+                }
+                if form == word {
+                    entry.forms.clear();
+                    case_insensitive = true;
+                } else {
+                    entry.forms.push(' ');
+                    entry.forms.push_str(form);
+                }
+            }
+            _ => {
+                case_insensitive = form == word;
+                let forms = match case_insensitive {
+                    true => String::new(),
+                    false => form.to_string(),
+                };
+                entries.push(Entry { word, forms });
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Parses the suggestion list, where every line holds a word and its popularity. The words are
+/// lowercased, and a word listed more than once is kept with its highest popularity.
+fn parse_suggestions(source: Source) -> Result<Vec<(Cow<str>, i64)>> {
+    let mut suggestions: Vec<(Cow<str>, i64)> = Vec::new();
+    let mut positions: HashMap<Cow<str>, usize> = HashMap::new(); // Where each word is in the list
+
+    for (i, line) in source.text.lines().enumerate() {
+        let error = |message: String| anyhow!("{}:{}: {message}", source.name, i + 1);
+
+        let mut columns = line.split_ascii_whitespace();
+        let (word, popularity) = match (columns.next(), columns.next(), columns.next()) {
+            (None, _, _) => continue,
+            (Some(word), Some(popularity), None) => (word, popularity),
+            (Some(word), None, _) => {
+                return Err(error(format!("'{word}' is missing its popularity")));
+            }
+            (Some(_), Some(_), Some(_)) => {
+                return Err(error("expected only a word and its popularity".to_string()));
+            }
+        };
+        let popularity: i64 = popularity
+            .parse()
+            .map_err(|_| error(format!("popularity '{popularity}' isn't a whole number")))?;
+
+        let word = match word.chars().any(char::is_uppercase) {
+            true => Cow::Owned(word.to_lowercase()),
+            false => Cow::Borrowed(word),
+        };
+        match positions.get(&word) {
+            Some(&position) => {
+                let entry = &mut suggestions[position].1;
+                *entry = (*entry).max(popularity);
+            }
+            None => {
+                positions.insert(word.clone(), suggestions.len());
+                suggestions.push((word, popularity));
+            }
+        }
+    }
+
+    Ok(suggestions)
+}
+
+fn compile(dict_source: Source, suggestion_source: Source, stamps: [Stamp; 2]) -> Result<Vec<u8>> {
+    let entries = parse_words(dict_source)?;
+    let suggestions = parse_suggestions(suggestion_source)?;
+
+    let (words, forms): (Vec<&str>, Vec<&str>) = entries
+        .iter()
+        .map(|entry| (entry.word.as_ref(), entry.forms.as_str()))
+        .unzip();
+    let (suggestions, popularities): (Vec<&str>, Vec<i64>) = suggestions
+        .iter()
+        .map(|(word, popularity)| (word.as_ref(), *popularity))
+        .unzip();
+
+    let mut bytes: Vec<u8> = Vec::new();
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    for stamp in stamps {
+        bytes.extend_from_slice(&stamp.size.to_le_bytes());
+        bytes.extend_from_slice(&stamp.modified.to_le_bytes());
+    }
+    write_table(&mut bytes, &words)?;
+    write_table(&mut bytes, &forms)?;
+    write_table(&mut bytes, &suggestions)?;
+    for popularity in popularities {
+        bytes.extend_from_slice(&popularity.to_le_bytes());
+    }
+    Ok(bytes)
+}
+
+fn write_table(bytes: &mut Vec<u8>, strings: &[&str]) -> Result<()> {
+    let too_large = || anyhow!("dictionary is too large to be compiled");
+
+    bytes.extend_from_slice(
+        &u32::try_from(strings.len())
+            .map_err(|_| too_large())?
+            .to_le_bytes(),
+    );
+    let mut offset: usize = 0;
+    for string in strings.iter().map(|s| s.len()).chain([0]) {
+        bytes.extend_from_slice(
+            &u32::try_from(offset)
+                .map_err(|_| too_large())?
+                .to_le_bytes(),
+        );
+        offset += string;
+    }
+    for string in strings {
+        bytes.extend_from_slice(string.as_bytes());
+    }
+    Ok(())
+}
+
+/// Returns the format version and the stamps of the sources stored in the header
+fn read_header(bytes: &[u8]) -> Result<(u32, [Stamp; 2])> {
+    if bytes.len() < HEADER_LEN || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(anyhow!("not a compiled dictionary"));
+    }
+    let version = read_u32(bytes, MAGIC.len())?;
+    let read_u64 =
+        |pos: usize| -> Result<u64> { Ok(u64::from_le_bytes(bytes[pos..pos + 8].try_into()?)) };
+    let stamps_start = MAGIC.len() + 4;
+    let stamp = |i: usize| -> Result<Stamp> {
+        let pos = stamps_start + i * 16;
+        Ok(Stamp {
+            size: read_u64(pos)?,
+            modified: read_u64(pos + 8)?,
+        })
+    };
+    Ok((version, [stamp(0)?, stamp(1)?]))
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> Result<u32> {
+    let range: Range<usize> = pos..pos + 4;
+    match bytes.get(range) {
+        Some(slice) => Ok(u32::from_le_bytes(slice.try_into()?)),
+        None => Err(anyhow!("compiled dictionary is truncated")),
+    }
+}
+
+/// Writes the file through a temporary file, so that other processes never see (or map) a
+/// partially written file.
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&temp_path, contents)?;
+    if let Err(error) = fs::rename(&temp_path, path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(error.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spellchecker::algorithm;
+    use crate::test_support::test_dir;
+    use std::time::Duration;
+
+    const WORDS: &str = "apple\napples\nbanana\n\nzażółć\n";
+    const SUGGESTIONS: &str = "the\t100\napple\t20\n";
+
+    #[test]
+    fn test_compiled_contents() {
+        let dictionary = Dictionary::from_sources(WORDS, SUGGESTIONS).unwrap();
+        assert_eq!(dictionary.len(), 4);
+        assert_eq!(dictionary.get_word(0), "apple");
+        assert_eq!(dictionary.get_word(3), "zażółć");
+        assert_eq!(
+            dictionary.suggestions().collect::<Vec<_>>(),
+            vec![("the", 100), ("apple", 20)]
+        );
+    }
+
+    #[test]
+    fn test_empty_dictionary() {
+        let dictionary = Dictionary::default();
+        assert!(dictionary.is_empty());
+        assert_eq!(dictionary.suggestions().count(), 0);
+    }
+
+    #[test]
+    fn test_invalid_suggestion_line() {
+        assert!(Dictionary::from_sources(WORDS, "the\t100\napple\n").is_err());
+        assert!(Dictionary::from_sources(WORDS, "the\tmany\n").is_err());
+    }
+
+    #[test]
+    fn test_normalising_words() {
+        let dictionary =
+            Dictionary::from_sources("banana\nApple\napple\n  kiwi \nBANANA\n", "").unwrap();
+        assert_eq!(dictionary.len(), 3);
+        assert_eq!(dictionary.get_word(0), "apple");
+        assert_eq!(dictionary.get_word(1), "banana");
+        assert_eq!(dictionary.get_word(2), "kiwi");
+        assert!(algorithm::is_word_correct("Banana", &dictionary));
+        assert!(algorithm::is_word_correct("kiwi", &dictionary));
+    }
+
+    #[test]
+    fn test_case_sensitive_words() {
+        let dictionary =
+            Dictionary::from_sources("London\nNASA\niPhone\nUS\nus\nMcDonald\nMCDONALD\n", "")
+                .unwrap();
+        assert_eq!(dictionary.lookup("London"), WordStatus::Correct);
+        assert_eq!(dictionary.lookup("LONDON"), WordStatus::Correct);
+        assert_eq!(
+            dictionary.lookup("london"),
+            WordStatus::WrongCase(vec!["London".to_string()])
+        );
+        assert_eq!(
+            dictionary.lookup("Nasa"),
+            WordStatus::WrongCase(vec!["NASA".to_string()])
+        );
+        assert_eq!(dictionary.lookup("iPhone"), WordStatus::Correct);
+        assert_eq!(dictionary.lookup("IPHONE"), WordStatus::Correct);
+        assert_eq!(
+            dictionary.lookup("Iphone"),
+            WordStatus::WrongCase(vec!["iPhone".to_string()])
+        );
+        assert_eq!(
+            dictionary.lookup("Mcdonald"),
+            WordStatus::WrongCase(vec!["MCDONALD".to_string(), "McDonald".to_string()])
+        );
+        // Listed in lowercase too, so any casing is correct
+        assert_eq!(dictionary.lookup("Us"), WordStatus::Correct);
+        assert_eq!(dictionary.lookup("Paris"), WordStatus::Unknown);
+
+        assert_eq!(dictionary.canonical_form("nasa"), "NASA");
+        assert_eq!(dictionary.canonical_form("us"), "us");
+        assert_eq!(dictionary.canonical_form("kiwi"), "kiwi");
+    }
+
+    #[test]
+    fn test_normalising_suggestions() {
+        let dictionary = Dictionary::from_sources("", "the 100\nThe 300\nof 50\nthe 7\n").unwrap();
+        assert_eq!(
+            dictionary.suggestions().collect::<Vec<_>>(),
+            vec![("the", 300), ("of", 50)]
+        );
+    }
+
+    #[test]
+    fn test_malformed_lines_reported() {
+        let error = Dictionary::from_sources("apple\nice cream\n", "")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "dict.txt:2: 'ice cream' contains whitespace, expected one word per line"
+        );
+
+        let error = Dictionary::from_sources("", "the\t100\n\nof\tmany\n")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "suggestion_dict.txt:3: popularity 'many' isn't a whole number"
+        );
+    }
+
+    #[test]
+    fn test_malformed_file_named() {
+        let dir = test_dir("dictionary-malformed");
+        let paths = DictionaryPaths {
+            dict: Some(dir.join("dict.txt")),
+            suggestion_dict: Some(dir.join("suggestion_dict.txt")),
+            compiled: None,
+            ..Default::default()
+        };
+        fs::write(paths.dict.as_ref().unwrap(), WORDS).unwrap();
+        fs::write(paths.suggestion_dict.as_ref().unwrap(), "the\t100\napple\n").unwrap();
+
+        let error = Dictionary::load(&paths).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            format!(
+                "{}:2: 'apple' is missing its popularity",
+                dir.join("suggestion_dict.txt").display()
+            )
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_header() {
+        let source = |text| Source { name: "test", text };
+        let stamps = [
+            Stamp {
+                size: 1,
+                modified: 2,
+            },
+            Stamp {
+                size: 3,
+                modified: 4,
+            },
+        ];
+        let bytes = compile(source(WORDS), source(SUGGESTIONS), stamps).unwrap();
+        assert_eq!(read_header(&bytes).unwrap(), (FORMAT_VERSION, stamps));
+        assert!(read_header(b"SPELCHE").is_err());
+        assert!(read_header(b"NOTADICTIONARYFILE...").is_err());
+        assert!(Dictionary::from_bytes(Bytes::Owned(bytes[..bytes.len() - 1].to_vec())).is_err());
+    }
+
+    #[test]
+    fn test_loading_compiled() {
+        let dir = test_dir("dictionary-compiled");
+        let paths = DictionaryPaths {
+            dict: Some(dir.join("dict.txt")),
+            suggestion_dict: Some(dir.join("suggestion_dict.txt")),
+            compiled: Some(dir.join("cache/dict.bin")),
+            ..Default::default()
+        };
+        let (dict, compiled) = (paths.dict.clone().unwrap(), paths.compiled.clone().unwrap());
+        fs::write(&dict, WORDS).unwrap();
+        fs::write(paths.suggestion_dict.as_ref().unwrap(), SUGGESTIONS).unwrap();
+
+        // First load compiles the sources
+        let dictionary = Dictionary::load(&paths).unwrap();
+        assert!(!dictionary.is_mapped());
+        assert!(compiled.exists());
+
+        // Then the compiled file is used
+        let dictionary = Dictionary::load(&paths).unwrap();
+        assert!(dictionary.is_mapped());
+        assert_eq!(dictionary.len(), 4);
+
+        // Changing the sources causes a rebuild
+        fs::write(&dict, "kiwi\n").unwrap();
+        let dictionary = Dictionary::load(&paths).unwrap();
+        assert!(!dictionary.is_mapped());
+        assert_eq!(dictionary.len(), 1);
+        assert_eq!(dictionary.get_word(0), "kiwi");
+
+        let dictionary = Dictionary::load(&paths).unwrap();
+        assert!(dictionary.is_mapped());
+        assert_eq!(dictionary.get_word(0), "kiwi");
+
+        // And so does changing them without changing their size, as long as they are modified
+        // later
+        fs::write(&dict, "pear\n").unwrap();
+        let later = fs::metadata(&compiled).unwrap().modified().unwrap() + Duration::from_secs(1);
+        File::options()
+            .write(true)
+            .open(&dict)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        let dictionary = Dictionary::load(&paths).unwrap();
+        assert!(!dictionary.is_mapped());
+        assert_eq!(dictionary.get_word(0), "pear");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_missing_sources() {
+        let dir = test_dir("dictionary-missing");
+        let result = Dictionary::load(&DictionaryPaths {
+            dict: None,
+            suggestion_dict: None,
+            compiled: Some(dir.join("dict.bin")),
+            ..Default::default()
+        });
+
+        if cfg!(feature = "embedded-dict") {
+            let dictionary = result.unwrap();
+            assert!(algorithm::is_word_correct("apple", &dictionary));
+            assert_eq!(dictionary.suggestions().next().unwrap().0, "the");
+            assert!(dir.join("dict.bin").exists());
+        } else {
+            assert!(result.is_err());
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,105 @@
+//! The bundled word lists and corrections corpus, compressed and embedded into the binary when it
+//! is built with the `embedded-dict` feature. They are used in place of the files in the program
+//! files directory when those aren't installed.
+
+use crate::prelude::*;
+
+/// The word lists that are bundled with the program
+#[derive(Debug, Clone, Copy)]
+pub enum List {
+    Dict,
+    SuggestionDict,
+    Corrections,
+}
+
+/// Whether the program was built with the word lists embedded
+pub fn is_available() -> bool {
+    cfg!(feature = "embedded-dict")
+}
+
+#[cfg(feature = "embedded-dict")]
+const DICT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/dict.txt.gz"));
+
+#[cfg(feature = "embedded-dict")]
+const SUGGESTION_DICT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/suggestion_dict.txt.gz"));
+
+#[cfg(feature = "embedded-dict")]
+const CORRECTIONS: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/corrections.txt.gz"));
+
+/// Returns the decompressed contents of the bundled word list, or `None` if the program was
+/// built without the word lists embedded.
+#[cfg(feature = "embedded-dict")]
+pub fn read(list: List) -> Option<Result<Vec<u8>>> {
+    use std::io::Read;
+
+    let compressed = match list {
+        List::Dict => DICT,
+        List::SuggestionDict => SUGGESTION_DICT,
+        List::Corrections => CORRECTIONS,
+    };
+
+    let mut contents: Vec<u8> = Vec::new();
+    Some(
+        flate2::read::GzDecoder::new(compressed)
+            .read_to_end(&mut contents)
+            .map(|_| contents)
+            .map_err(|error| error.into()),
+    )
+}
+
+/// Returns the decompressed contents of the bundled word list, or `None` if the program was
+/// built without the word lists embedded.
+#[cfg(not(feature = "embedded-dict"))]
+pub fn read(_list: List) -> Option<Result<Vec<u8>>> {
+    None
+}
+
+/// Returns the size of the decompressed bundled word list and a hash of its contents, computed
+/// when the program was built, or `None` if it was built without the word lists embedded.
+#[cfg(feature = "embedded-dict")]
+pub fn identity(list: List) -> Option<(u64, u64)> {
+    let (size, hash) = match list {
+        List::Dict => (env!("SPELCHEK_DICT_SIZE"), env!("SPELCHEK_DICT_HASH")),
+        List::SuggestionDict => (
+            env!("SPELCHEK_SUGGESTION_DICT_SIZE"),
+            env!("SPELCHEK_SUGGESTION_DICT_HASH"),
+        ),
+        List::Corrections => (
+            env!("SPELCHEK_CORRECTIONS_SIZE"),
+            env!("SPELCHEK_CORRECTIONS_HASH"),
+        ),
+    };
+    Some((size.parse().ok()?, hash.parse().ok()?))
+}
+
+/// Returns the size of the decompressed bundled word list and a hash of its contents, computed
+/// when the program was built, or `None` if it was built without the word lists embedded.
+#[cfg(not(feature = "embedded-dict"))]
+pub fn identity(_list: List) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(all(test, feature = "embedded-dict"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reading_embedded_lists() {
+        let dict = read(List::Dict).unwrap().unwrap();
+        assert!(dict.starts_with(b"a\n"));
+
+        let suggestion_dict = read(List::SuggestionDict).unwrap().unwrap();
+        assert!(suggestion_dict.starts_with(b"the\t"));
+
+        let corrections = read(List::Corrections).unwrap().unwrap();
+        assert!(corrections.starts_with(b"abandonned\tabandoned\n"));
+    }
+
+    #[test]
+    fn test_identity() {
+        let dict = read(List::Dict).unwrap().unwrap();
+        let (size, _) = identity(List::Dict).unwrap();
+        assert_eq!(size, dict.len() as u64);
+        assert_ne!(identity(List::Dict), identity(List::SuggestionDict));
+    }
+}
@@ -0,0 +1,148 @@
+//! The corrections the user has accepted before.
+//!
+//! Every accepted suggestion is appended to the history file as a line holding the misspelled
+//! word and its correction, separated by a tab. Corrections accepted for a misspelling before are
+//! suggested first the next time it is made, the most often accepted one at the top.
+
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::prelude::*;
+
+#[derive(Debug, Default, Clone)]
+pub struct CorrectionHistory {
+    path: Option<PathBuf>, // The file the history is read from and recorded to
+    // How many times each correction of a misspelling was accepted, all lowercased
+    corrections: HashMap<String, HashMap<String, u32>>,
+}
+
+impl CorrectionHistory {
+    /// Reads the history file at `path`. The file doesn't have to exist yet - it is created once
+    /// a correction is recorded.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut history = match fs::read(path) {
+            Ok(contents) => Self::from_source(&String::from_utf8_lossy(&contents)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(error) => {
+                return Err(error).with_context(|| format!("couldn't read {}", path.display()));
+            }
+        };
+        history.path = Some(path.to_path_buf());
+        Ok(history)
+    }
+
+    /// Parses the contents of a history file. Lines which aren't a pair of words are skipped, so
+    /// that a damaged history never stops the program from starting.
+    pub fn from_source(source: &str) -> Self {
+        let mut history = CorrectionHistory::default();
+        for line in source.lines() {
+            if let Some((misspelling, correction)) = line.split_once('\t') {
+                history.add(misspelling, correction);
+            }
+        }
+        history
+    }
+
+    fn add(&mut self, misspelling: &str, correction: &str) {
+        let (misspelling, correction) = (misspelling.trim(), correction.trim());
+        if misspelling.is_empty() || correction.is_empty() {
+            return;
+        }
+        *self
+            .corrections
+            .entry(misspelling.to_lowercase())
+            .or_default()
+            .entry(correction.to_lowercase())
+            .or_default() += 1;
+    }
+
+    /// Records that the misspelling was corrected to `correction`, appending it to the history
+    /// file if there is one.
+    pub fn record(&mut self, misspelling: &str, correction: &str) -> Result<()> {
+        if misspelling.contains(['\t', '\n']) || correction.contains(['\t', '\n']) {
+            return Ok(());
+        }
+        self.add(misspelling, correction);
+
+        if let Some(path) = &self.path {
+            append_correction(path, misspelling, correction)
+                .with_context(|| format!("couldn't record the correction to {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the corrections accepted for the misspelling before, with the number of times each
+    /// of them was accepted
+    pub fn corrections_of(&self, misspelling: &str) -> Option<&HashMap<String, u32>> {
+        self.corrections.get(&misspelling.to_lowercase())
+    }
+
+    /// Returns every misspelling with its corrections, and the number of times each was accepted
+    pub fn pairs(&self) -> impl Iterator<Item = (&str, &str, u32)> + '_ {
+        self.corrections
+            .iter()
+            .flat_map(|(misspelling, corrections)| {
+                corrections.iter().map(move |(correction, &times)| {
+                    (misspelling.as_str(), correction.as_str(), times)
+                })
+            })
+    }
+
+    /// The number of times the misspelling was corrected to `correction`
+    pub fn times_accepted(&self, misspelling: &str, correction: &str) -> u32 {
+        self.corrections_of(misspelling)
+            .and_then(|corrections| corrections.get(&correction.to_lowercase()))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+fn append_correction(path: &Path, misspelling: &str, correction: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{misspelling}\t{correction}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_dir;
+
+    #[test]
+    fn test_parsing_history() {
+        let history =
+            CorrectionHistory::from_source("teh\tthe\nTeh\tThe\nteh\tten\nbroken line\n\t\n");
+        assert_eq!(history.times_accepted("teh", "the"), 2);
+        assert_eq!(history.times_accepted("TEH", "ten"), 1);
+        assert_eq!(history.times_accepted("teh", "tea"), 0);
+        assert!(history.corrections_of("broken").is_none());
+    }
+
+    #[test]
+    fn test_recording_corrections() {
+        // The directory of the history is created with it
+        let dir = test_dir("history");
+        let path = dir.join("spelchek/history.tsv");
+
+        let mut history = CorrectionHistory::load(&path).unwrap();
+        history.record("recieve", "receive").unwrap();
+        history.record("Recieve", "Receive").unwrap();
+        assert_eq!(history.times_accepted("recieve", "receive"), 2);
+
+        let history = CorrectionHistory::load(&path).unwrap();
+        assert_eq!(history.times_accepted("recieve", "receive"), 2);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "recieve\treceive\nRecieve\tReceive\n"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,310 @@
+//! The stack of dictionaries a word is looked up in.
+//!
+//! On top of the base dictionary there can be a project word list shared by a team (found by
+//! walking up from the checked file), the user's personal word list, and the words accepted for
+//! the current session only. A word is correct if any of the layers accepts it.
+
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+
+use super::dictionary::{Dictionary, WordStatus};
+use crate::prelude::*;
+
+/// The kinds of layers, in the order they are stacked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LayerKind {
+    /// The dictionary of the language
+    Base,
+    /// The word list committed to the repository of the checked file
+    Project,
+    /// The user's personal word list
+    User,
+    /// Words accepted until the program exits
+    Session,
+}
+
+impl fmt::Display for LayerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LayerKind::Base => "base",
+            LayerKind::Project => "project",
+            LayerKind::User => "user",
+            LayerKind::Session => "session",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug)]
+pub struct Layer {
+    pub kind: LayerKind,
+    pub path: Option<PathBuf>, // The word list the layer is read from and added to
+    words: Vec<String>,        // The words of a word list layer, empty for the base dictionary
+    dict: Dictionary,
+}
+
+impl Layer {
+    /// Creates a layer from a list of words, which are also used for suggestions.
+    fn from_words(kind: LayerKind, path: Option<PathBuf>, words: Vec<String>) -> Result<Self> {
+        // The words have no known popularity, so they are suggested after the base dictionary's
+        // words of the same edit distance
+        let suggestion_source: String = words.iter().map(|word| format!("{word} 0\n")).collect();
+        let dict = Dictionary::from_sources(&words.join("\n"), &suggestion_source)?;
+        Ok(Layer {
+            kind,
+            path,
+            words,
+            dict,
+        })
+    }
+
+    pub fn dictionary(&self) -> &Dictionary {
+        &self.dict
+    }
+}
+
+#[derive(Debug)]
+pub struct DictionaryStack {
+    layers: Vec<Layer>, // Always sorted by kind, with at most one layer of each kind
+}
+
+impl Default for DictionaryStack {
+    fn default() -> Self {
+        DictionaryStack::new(Dictionary::default())
+    }
+}
+
+impl DictionaryStack {
+    /// Creates a stack of the base dictionary and an empty session layer
+    pub fn new(base: Dictionary) -> Self {
+        DictionaryStack {
+            layers: vec![
+                Layer {
+                    kind: LayerKind::Base,
+                    path: None,
+                    words: Vec::new(),
+                    dict: base,
+                },
+                Layer::from_words(LayerKind::Session, None, Vec::new())
+                    .expect("empty word lists always compile"),
+            ],
+        }
+    }
+
+    /// Adds a layer read from the word list at `path`, replacing the layer of the same kind if
+    /// there is one. The file doesn't have to exist yet - it is created once a word is added.
+    pub fn load_word_list(&mut self, kind: LayerKind, path: &Path) -> Result<()> {
+        let words: Vec<String> = match fs::read(path) {
+            Ok(contents) => String::from_utf8_lossy(&contents)
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string)
+                .collect(),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => {
+                return Err(error).with_context(|| format!("couldn't read {}", path.display()));
+            }
+        };
+
+        let layer = Layer::from_words(kind, Some(path.to_path_buf()), words)
+            .with_context(|| format!("invalid word list {}", path.display()))?;
+        self.insert(layer);
+        Ok(())
+    }
+
+    fn insert(&mut self, layer: Layer) {
+        self.layers.retain(|other| other.kind != layer.kind);
+        let position = self
+            .layers
+            .iter()
+            .position(|other| other.kind > layer.kind)
+            .unwrap_or(self.layers.len());
+        self.layers.insert(position, layer);
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn layer(&self, kind: LayerKind) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.kind == kind)
+    }
+
+    /// The dictionary of the base layer
+    pub fn base(&self) -> &Dictionary {
+        &self.layers[0].dict
+    }
+
+    /// Looks the word up in every layer. A word is correct if any layer accepts it, and is in
+    /// the wrong case if none does but some layer has it in a different casing. Also returns the
+    /// layer which decided that.
+    pub fn lookup(&self, word: &str) -> (WordStatus, Option<LayerKind>) {
+        let mut result = (WordStatus::Unknown, None);
+
+        for layer in &self.layers {
+            match layer.dict.lookup(word) {
+                WordStatus::Correct => return (WordStatus::Correct, Some(layer.kind)),
+                WordStatus::WrongCase(forms) if result.1.is_none() => {
+                    result = (WordStatus::WrongCase(forms), Some(layer.kind));
+                }
+                _ => {}
+            }
+        }
+        result
+    }
+
+    /// Returns the word in its canonical casing if any layer has one, otherwise the word
+    /// unchanged
+    pub fn canonical_form<'a>(&'a self, word: &'a str) -> &'a str {
+        self.layers
+            .iter()
+            .map(|layer| layer.dict.canonical_form(word))
+            .find(|form| *form != word)
+            .unwrap_or(word)
+    }
+
+    /// Returns the popularity of the word in the first layer which suggests it
+    pub fn popularity(&self, word: &str) -> Option<i64> {
+        self.layers
+            .iter()
+            .find_map(|layer| layer.dict.popularity(word))
+    }
+
+    /// Adds the word to a layer. Words added to a layer backed by a word list are also appended
+    /// to the file, which is created if it doesn't exist.
+    pub fn add_word(&mut self, kind: LayerKind, word: &str) -> Result<()> {
+        let layer = self
+            .layers
+            .iter_mut()
+            .find(|layer| layer.kind == kind)
+            .filter(|layer| layer.kind != LayerKind::Base)
+            .ok_or_else(|| anyhow!("there is no {kind} dictionary to add words to"))?;
+
+        if word.is_empty() || word.contains(char::is_whitespace) {
+            return Err(anyhow!("only single words can be added to a dictionary"));
+        }
+
+        if let Some(path) = &layer.path {
+            append_word(path, word)
+                .with_context(|| format!("couldn't add '{word}' to {}", path.display()))?;
+        }
+
+        let mut words = layer.words.clone();
+        words.push(word.to_string());
+        *layer = Layer::from_words(kind, layer.path.clone(), words)?;
+        Ok(())
+    }
+}
+
+fn append_word(path: &Path, word: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    // Make sure the word goes onto a line of its own
+    let needs_newline = fs::read(path)
+        .map(|contents| !contents.is_empty() && !contents.ends_with(b"\n"))
+        .unwrap_or(false);
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if needs_newline {
+        file.write_all(b"\n")?;
+    }
+    writeln!(file, "{word}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_dir;
+
+    #[test]
+    fn test_lookup_through_layers() {
+        let dir = test_dir("layers-lookup");
+        fs::write(dir.join("project.txt"), "kubectl\nlondon\n").unwrap();
+        fs::write(dir.join("user.txt"), "# My words\nGitLab").unwrap();
+
+        let base = Dictionary::from_sources("apple\nLondon\n", "").unwrap();
+        let mut stack = DictionaryStack::new(base);
+        stack
+            .load_word_list(LayerKind::User, &dir.join("user.txt"))
+            .unwrap();
+        stack
+            .load_word_list(LayerKind::Project, &dir.join("project.txt"))
+            .unwrap();
+
+        let kinds: Vec<LayerKind> = stack.layers().iter().map(|layer| layer.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                LayerKind::Base,
+                LayerKind::Project,
+                LayerKind::User,
+                LayerKind::Session
+            ]
+        );
+
+        assert_eq!(
+            stack.lookup("apple"),
+            (WordStatus::Correct, Some(LayerKind::Base))
+        );
+        assert_eq!(
+            stack.lookup("kubectl"),
+            (WordStatus::Correct, Some(LayerKind::Project))
+        );
+        // The project list accepts any casing of the word
+        assert_eq!(
+            stack.lookup("london"),
+            (WordStatus::Correct, Some(LayerKind::Project))
+        );
+        assert_eq!(
+            stack.lookup("Gitlab"),
+            (
+                WordStatus::WrongCase(vec!["GitLab".to_string()]),
+                Some(LayerKind::User)
+            )
+        );
+        assert_eq!(stack.lookup("kiwi"), (WordStatus::Unknown, None));
+        assert_eq!(stack.canonical_form("gitlab"), "GitLab");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_adding_words() {
+        let dir = test_dir("layers-adding");
+        let user_words = dir.join("words/user.txt");
+
+        let mut stack = DictionaryStack::default();
+        stack.load_word_list(LayerKind::User, &user_words).unwrap();
+
+        stack.add_word(LayerKind::Session, "spelchek").unwrap();
+        assert_eq!(
+            stack.lookup("spelchek"),
+            (WordStatus::Correct, Some(LayerKind::Session))
+        );
+
+        stack.add_word(LayerKind::User, "kubectl").unwrap();
+        stack.add_word(LayerKind::User, "ratatui").unwrap();
+        assert_eq!(
+            stack.lookup("ratatui"),
+            (WordStatus::Correct, Some(LayerKind::User))
+        );
+        assert_eq!(
+            fs::read_to_string(&user_words).unwrap(),
+            "kubectl\nratatui\n"
+        );
+
+        assert!(stack.add_word(LayerKind::Project, "word").is_err());
+        assert!(stack.add_word(LayerKind::Session, "new york").is_err());
+        assert!(stack.add_word(LayerKind::Base, "word").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,316 @@
+//! Multi-word terms, like "New York", "ad hoc" or "Visual Studio Code".
+//!
+//! The phrase list holds one phrase per line, written in its canonical casing, and lines starting
+//! with `#` are comments. Phrases are matched against consecutive words of the token stream which
+//! are separated only by whitespace. The words of a matched phrase are accepted together (so "hoc"
+//! is correct in "ad hoc"), while a phrase written in the wrong case, or with a misspelled word in
+//! it, is reported and corrected as a whole.
+
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::Context;
+
+use super::algorithm::edit_distance;
+use crate::prelude::*;
+
+#[derive(Debug, Clone)]
+struct Phrase {
+    text: String,      // In the canonical casing, with the words separated by single spaces
+    lowercase: String, // `text` lowercased
+    word_count: usize,
+}
+
+/// How the words at some position in the text match a phrase.
+#[derive(Debug, PartialEq)]
+pub enum PhraseMatch<'a> {
+    /// The next `len` words are the phrase, written correctly
+    Correct { len: usize },
+    /// The next `len` words are the phrase written in the wrong case
+    WrongCase { len: usize, phrase: &'a str },
+    /// The next `len` words are the phrase with a misspelling in it
+    Misspelled { len: usize, phrase: &'a str },
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct PhraseList {
+    phrases: Vec<Phrase>, // Sorted from the longest, so that longer phrases are matched first
+    by_first_word: HashMap<String, Vec<usize>>, // Indices of the phrases by their lowercased
+    // first word
+    max_word_count: usize,
+}
+
+impl PhraseList {
+    /// Loads the phrase list at `path`, or an empty list if there is none.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => Ok(Self::from_source(
+                &fs::read_to_string(path)
+                    .with_context(|| format!("couldn't read {}", path.display()))?,
+            )),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn from_source(source: &str) -> Self {
+        let mut phrases: Vec<Phrase> = source
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let words: Vec<&str> = line.split_whitespace().collect();
+                let text = words.join(" ");
+                Phrase {
+                    lowercase: text.to_lowercase(),
+                    text,
+                    word_count: words.len(),
+                }
+            })
+            .collect();
+        phrases.sort_by(|a, b| b.word_count.cmp(&a.word_count).then(a.text.cmp(&b.text)));
+        phrases.dedup_by(|a, b| a.text == b.text);
+
+        let mut by_first_word: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, phrase) in phrases.iter().enumerate() {
+            let first_word = phrase.lowercase.split(' ').next().unwrap_or_default();
+            by_first_word
+                .entry(first_word.to_string())
+                .or_default()
+                .push(i);
+        }
+
+        PhraseList {
+            max_word_count: phrases.first().map_or(0, |phrase| phrase.word_count),
+            phrases,
+            by_first_word,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.phrases.is_empty()
+    }
+
+    /// The number of words of the longest phrase
+    pub fn max_word_count(&self) -> usize {
+        self.max_word_count
+    }
+
+    /// Matches the phrases against the words at the start of `words` (byte ranges into
+    /// `buffer`). `is_unknown` tells whether a word is missing from the dictionary - a phrase is
+    /// only considered misspelled if one of its words is, so that correct words close to a phrase
+    /// aren't flagged.
+    pub fn match_at(
+        &self,
+        buffer: &str,
+        words: &[Range<usize>],
+        is_unknown: &dyn Fn(&str) -> bool,
+    ) -> Option<PhraseMatch<'_>> {
+        if self.is_empty() || words.is_empty() {
+            return None;
+        }
+
+        let window = |len: usize| Window::new(buffer, words, len);
+
+        // The longest phrase the words are, regardless of casing
+        let first_word = buffer[words[0].clone()].to_lowercase();
+        let exact = self
+            .by_first_word
+            .get(&first_word)
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.phrases[i])
+            .find_map(|phrase| match window(phrase.word_count) {
+                Some(window) if window.lowercase == phrase.lowercase => Some((phrase, window)),
+                _ => None,
+            });
+
+        // A longer phrase with a misspelling in it takes precedence, so that "Visual Studio Cod"
+        // is corrected to "Visual Studio Code" rather than accepted as "Visual Studio"
+        let min_len = exact
+            .as_ref()
+            .map_or(2, |(phrase, _)| phrase.word_count + 1);
+        if let Some(phrase) = self.closest_misspelled(buffer, words, min_len, is_unknown) {
+            return Some(PhraseMatch::Misspelled {
+                len: phrase.word_count,
+                phrase: &phrase.text,
+            });
+        }
+
+        let (phrase, window) = exact?;
+        if window.matches_casing(&phrase.text) {
+            return Some(PhraseMatch::Correct {
+                len: phrase.word_count,
+            });
+        }
+        // Only reported within a line, since a misspelling can't span multiple lines
+        match window.spans_lines {
+            true => None,
+            false => Some(PhraseMatch::WrongCase {
+                len: phrase.word_count,
+                phrase: &phrase.text,
+            }),
+        }
+    }
+
+    /// Returns the phrase of at least `min_len` words closest to the start of `words`, if they
+    /// are likely a misspelling of it.
+    fn closest_misspelled(
+        &self,
+        buffer: &str,
+        words: &[Range<usize>],
+        min_len: usize,
+        is_unknown: &dyn Fn(&str) -> bool,
+    ) -> Option<&Phrase> {
+        let has_unknown = |len: usize| {
+            words[..len.min(words.len())]
+                .iter()
+                .any(|word| is_unknown(&buffer[word.clone()]))
+        };
+        if !has_unknown(self.max_word_count) {
+            return None;
+        }
+
+        let mut best: Option<(i32, &Phrase)> = None;
+        for phrase in &self.phrases {
+            if phrase.word_count < min_len || !has_unknown(phrase.word_count) {
+                continue;
+            }
+            let window = match Window::new(buffer, words, phrase.word_count) {
+                Some(window) if !window.spans_lines => window,
+                _ => continue,
+            };
+
+            let distance = edit_distance(&window.lowercase, &phrase.lowercase);
+            if distance <= max_distance(&phrase.lowercase)
+                && best.is_none_or(|(best_distance, _)| distance < best_distance)
+            {
+                best = Some((distance, phrase));
+            }
+        }
+
+        best.map(|(_, phrase)| phrase)
+    }
+}
+
+/// The number of edits a misspelled phrase may be away from the phrase
+fn max_distance(phrase: &str) -> i32 {
+    (phrase.chars().count() / 4).clamp(1, 3) as i32
+}
+
+/// A number of consecutive words of the text, which could be a phrase.
+struct Window<'a> {
+    words: Vec<&'a str>,
+    lowercase: String, // The lowercased words, separated by single spaces
+    spans_lines: bool,
+}
+
+impl<'a> Window<'a> {
+    /// Returns the first `len` words, if there are that many, and they are separated by nothing
+    /// but whitespace.
+    fn new(buffer: &'a str, words: &[Range<usize>], len: usize) -> Option<Self> {
+        let words = words.get(..len)?;
+
+        let mut spans_lines = false;
+        for pair in words.windows(2) {
+            let between = &buffer[pair[0].end..pair[1].start];
+            if !between.chars().all(char::is_whitespace) {
+                return None;
+            }
+            spans_lines = spans_lines || between.contains('\n');
+        }
+
+        let words: Vec<&str> = words.iter().map(|word| &buffer[word.clone()]).collect();
+        Some(Window {
+            lowercase: words.join(" ").to_lowercase(),
+            words,
+            spans_lines,
+        })
+    }
+
+    /// Whether the words are written like `phrase`, or in all capitals
+    fn matches_casing(&self, phrase: &str) -> bool {
+        let is_shouted = !self
+            .words
+            .iter()
+            .any(|word| word.chars().any(char::is_lowercase));
+        let phrase = match is_shouted {
+            true => phrase.to_uppercase(),
+            false => phrase.to_string(),
+        };
+        phrase.split(' ').eq(self.words.iter().copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spellchecker::tokenizer::{PlainText, Tokenizer};
+
+    const PHRASES: &str = "# Places\nNew York\nad hoc\nVisual Studio\nVisual Studio Code\n";
+
+    fn match_text<'a>(phrases: &'a PhraseList, text: &str) -> Option<PhraseMatch<'a>> {
+        let known = ["new", "york", "ad", "visual", "studio", "code", "the"];
+        phrases.match_at(text, &PlainText.tokenize(text), &|word: &str| {
+            !known.contains(&word.to_lowercase().as_str())
+        })
+    }
+
+    #[test]
+    fn test_correct_phrases() {
+        let phrases = PhraseList::from_source(PHRASES);
+        assert_eq!(
+            match_text(&phrases, "New York"),
+            Some(PhraseMatch::Correct { len: 2 })
+        );
+        assert_eq!(
+            match_text(&phrases, "NEW YORK city"),
+            Some(PhraseMatch::Correct { len: 2 })
+        );
+        assert_eq!(
+            match_text(&phrases, "Visual Studio Code"),
+            Some(PhraseMatch::Correct { len: 3 })
+        );
+        assert_eq!(
+            match_text(&phrases, "ad\nhoc"),
+            Some(PhraseMatch::Correct { len: 2 })
+        );
+        assert_eq!(match_text(&phrases, "New, York"), None);
+        assert_eq!(match_text(&phrases, "the New York"), None);
+    }
+
+    #[test]
+    fn test_wrongly_cased_phrases() {
+        let phrases = PhraseList::from_source(PHRASES);
+        assert_eq!(
+            match_text(&phrases, "new york"),
+            Some(PhraseMatch::WrongCase {
+                len: 2,
+                phrase: "New York"
+            })
+        );
+        assert_eq!(match_text(&phrases, "new\nyork"), None);
+    }
+
+    #[test]
+    fn test_misspelled_phrases() {
+        let phrases = PhraseList::from_source(PHRASES);
+        assert_eq!(
+            match_text(&phrases, "ad hok"),
+            Some(PhraseMatch::Misspelled {
+                len: 2,
+                phrase: "ad hoc"
+            })
+        );
+        assert_eq!(
+            match_text(&phrases, "visual studio cod"),
+            Some(PhraseMatch::Misspelled {
+                len: 3,
+                phrase: "Visual Studio Code"
+            })
+        );
+        // Correct words near a phrase aren't a misspelling of it
+        assert_eq!(match_text(&phrases, "new code"), None);
+    }
+}
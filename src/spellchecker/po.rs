@@ -0,0 +1,349 @@
+//! Extraction of the checkable parts of gettext `.po`/`.pot` translation files.
+//!
+//! Only the contents of the quoted strings are ever spellchecked. Comments (including `#,` flags
+//! and obsolete `#~` entries), keywords, `msgctxt` strings and the header entry are skipped, as are
+//! escape sequences and format placeholders like `%s`, `%1$d`, `%(name)s` or `{name}` inside the
+//! strings. The returned ranges are byte ranges into the whole buffer, so misspellings found in
+//! them can be corrected in place, even when a message spans multiple quoted lines.
+
+use std::ops::Range;
+
+use super::tokenizer::{words_in, Tokenizer};
+
+/// Tokenizer for gettext translation files. Checks the translations, and the source strings too
+/// if `check_msgid` is set. The source strings are returned as the words of the source language,
+/// so that they are checked with its dictionary rather than the translations' one.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PoFile {
+    pub check_msgid: bool,
+}
+
+impl Tokenizer for PoFile {
+    fn tokenize(&self, buffer: &str) -> Vec<Range<usize>> {
+        words_in(buffer, &checkable_ranges(buffer).translations)
+    }
+
+    fn tokenize_source(&self, buffer: &str) -> Vec<Range<usize>> {
+        match self.check_msgid {
+            true => words_in(buffer, &checkable_ranges(buffer).sources),
+            false => Vec::new(),
+        }
+    }
+}
+
+/// The byte ranges of the quoted strings of a translation file which should be spellchecked,
+/// each sorted
+#[derive(Debug, Default, PartialEq)]
+pub struct CheckableRanges {
+    pub sources: Vec<Range<usize>>, // The source strings, `msgid` and `msgid_plural`
+    pub translations: Vec<Range<usize>>, // The translations, `msgstr`
+}
+
+/// The part of an entry the quoted strings on the current line belong to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    None,
+    Context,
+    Id,
+    Str,
+}
+
+/// Returns the byte ranges of `buffer` that should be spellchecked, the source strings apart from
+/// the translations.
+pub fn checkable_ranges(buffer: &str) -> CheckableRanges {
+    let mut ranges = CheckableRanges::default();
+
+    let mut field = Field::None;
+    let mut msgid_empty = true; // Whether the `msgid` of the current entry is "", which means that
+                                // the entry is the header and its `msgstr` holds metadata
+
+    // The translations of the current entry. Those are kept aside until the end of the entry,
+    // because the `msgid` has to be fully read before it's known whether the entry is the header.
+    let mut pending_msgstr: Vec<Range<usize>> = Vec::new();
+
+    let mut line_start: usize = 0;
+    for line in buffer.split_inclusive('\n') {
+        let offset = line_start;
+        line_start += line.len();
+
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+
+        if trimmed.starts_with('#') || trimmed.trim().is_empty() {
+            continue;
+        }
+
+        if !trimmed.starts_with('"') {
+            let keyword = trimmed
+                .split(|c: char| c.is_whitespace() || c == '"')
+                .next()
+                .unwrap_or("");
+
+            let new_field = match keyword {
+                "msgctxt" => Field::Context,
+                "msgid" | "msgid_plural" => Field::Id,
+                k if k == "msgstr" || k.starts_with("msgstr[") => Field::Str,
+                _ => Field::None,
+            };
+
+            // `msgctxt` or `msgid` after a `msgstr` start a new entry
+            if field == Field::Str && new_field != Field::Str {
+                if !msgid_empty {
+                    ranges.translations.append(&mut pending_msgstr);
+                }
+                pending_msgstr.clear();
+            }
+            if keyword == "msgid" {
+                msgid_empty = true;
+            }
+            field = new_field;
+        }
+
+        let string = match quoted_string(line, indent) {
+            Some(string) => string,
+            None => continue,
+        };
+
+        match field {
+            Field::Id => {
+                msgid_empty = msgid_empty && string.is_empty();
+                ranges.sources.extend(split_string(line, string, offset));
+            }
+            Field::Str => pending_msgstr.extend(split_string(line, string, offset)),
+            Field::Context | Field::None => {}
+        }
+    }
+
+    if !msgid_empty {
+        ranges.translations.append(&mut pending_msgstr);
+    }
+    ranges
+}
+
+/// Returns the byte range (relative to the line) of the contents of the first quoted string on
+/// the line, searching from `from`. The quotes themselves are not included.
+fn quoted_string(line: &str, from: usize) -> Option<Range<usize>> {
+    let start = line[from..].find('"')? + from + 1;
+
+    let mut escaped = false;
+    for (i, c) in line[start..].char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(start..start + i),
+            _ => {}
+        }
+    }
+
+    None // Unterminated string
+}
+
+/// Splits the contents of a quoted string into the ranges between escape sequences and format
+/// placeholders, and moves them by `offset` so that they index the whole buffer.
+fn split_string(line: &str, string: Range<usize>, offset: usize) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let text = &line[string.clone()];
+    let bytes = text.as_bytes();
+
+    let mut push = |start: usize, end: usize| {
+        if start < end {
+            ranges.push(offset + string.start + start..offset + string.start + end);
+        }
+    };
+
+    let mut segment_start: usize = 0;
+    let mut i: usize = 0;
+    while i < bytes.len() {
+        let skip = match bytes[i] {
+            b'\\' => escape_len(&bytes[i..]),
+            b'%' => printf_placeholder_len(&bytes[i..]),
+            b'{' => brace_placeholder_len(&bytes[i..]),
+            _ => 0,
+        };
+
+        if skip == 0 {
+            i += 1;
+            continue;
+        }
+
+        push(segment_start, i);
+        i += skip;
+        segment_start = i;
+    }
+    push(segment_start, bytes.len());
+
+    ranges
+}
+
+/// The length of the escape sequence at the start of `bytes`, e.g. `\n`, `\"`, `\x41` or `\101`.
+fn escape_len(bytes: &[u8]) -> usize {
+    match bytes.get(1) {
+        None => 1,
+        Some(b'x') => {
+            2 + bytes[2..]
+                .iter()
+                .take_while(|b| b.is_ascii_hexdigit())
+                .count()
+        }
+        Some(b'0'..=b'7') => {
+            1 + bytes[1..]
+                .iter()
+                .take(3)
+                .take_while(|b| (b'0'..=b'7').contains(b))
+                .count()
+        }
+        // Multibyte characters are never part of an escape sequence, so only the backslash is
+        // skipped, which keeps the ranges on character boundaries.
+        Some(b) if !b.is_ascii() => 1,
+        Some(_) => 2,
+    }
+}
+
+/// The length of the printf-style placeholder at the start of `bytes` (like `%s`, `%1$d`, `%-5.2f`,
+/// `%(name)s` or `%%`), or 0 if there is none.
+fn printf_placeholder_len(bytes: &[u8]) -> usize {
+    let mut i: usize = 1;
+
+    match bytes.get(i) {
+        Some(b'%') => return 2,
+        Some(b'(') => match bytes[i..].iter().position(|&b| b == b')') {
+            Some(close) => i += close + 1,
+            None => return 0,
+        },
+        _ => {}
+    }
+
+    // Argument position, flags, width, precision and length modifiers
+    while let Some(b) = bytes.get(i) {
+        match b {
+            b'0'..=b'9' | b'$' | b'#' | b'-' | b'+' | b'\'' | b'.' | b'*' => i += 1,
+            b'h' | b'l' | b'L' | b'q' | b'j' | b'z' | b't' => i += 1,
+            _ => break,
+        }
+    }
+
+    match bytes.get(i) {
+        Some(b) if b.is_ascii_alphabetic() => i + 1,
+        _ => 0,
+    }
+}
+
+/// The length of the brace placeholder at the start of `bytes` (like `{name}`, `{0}` or a `{{`
+/// escape), or 0 if there is none.
+fn brace_placeholder_len(bytes: &[u8]) -> usize {
+    if bytes.get(1) == Some(&b'{') {
+        return 2;
+    }
+
+    match bytes.iter().position(|&b| b == b'}') {
+        // Placeholders never contain whitespace, anything else is just text in braces
+        Some(close) if !bytes[1..close].iter().any(|b| b.is_ascii_whitespace()) => close + 1,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checked_text(buffer: &str, check_msgid: bool) -> Vec<&str> {
+        let CheckableRanges {
+            sources,
+            mut translations,
+        } = checkable_ranges(buffer);
+        if check_msgid {
+            translations.extend(sources);
+            translations.sort_by_key(|range| range.start);
+        }
+        translations
+            .into_iter()
+            .map(|range| &buffer[range])
+            .collect()
+    }
+
+    const PO_FILE: &str = r#"# Translator comment
+msgid ""
+msgstr ""
+"Content-Type: text/plain; charset=UTF-8\n"
+
+#: src/main.c:12
+#, c-format
+msgid "Hello %s"
+msgstr "Bonjour %s"
+
+msgctxt "menu"
+msgid "Open"
+msgstr "Ouvrir"
+
+msgid ""
+"Multiple lines "
+"of text"
+msgstr ""
+"Plusieurs lignes\n"
+"de {count} texte"
+"#;
+
+    #[test]
+    fn test_only_msgstr_checked() {
+        assert_eq!(
+            checked_text(PO_FILE, false),
+            vec!["Bonjour ", "Ouvrir", "Plusieurs lignes", "de ", " texte"]
+        );
+    }
+
+    #[test]
+    fn test_msgid_checked() {
+        assert_eq!(
+            checked_text(PO_FILE, true),
+            vec![
+                "Hello ",
+                "Bonjour ",
+                "Open",
+                "Ouvrir",
+                "Multiple lines ",
+                "of text",
+                "Plusieurs lignes",
+                "de ",
+                " texte"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plural_forms() {
+        let po =
+            "msgid \"file\"\nmsgid_plural \"files\"\nmsgstr[0] \"plik\"\nmsgstr[1] \"pliki\"\n";
+        assert_eq!(checked_text(po, false), vec!["plik", "pliki"]);
+        assert_eq!(
+            checked_text(po, true),
+            vec!["file", "files", "plik", "pliki"]
+        );
+    }
+
+    #[test]
+    fn test_obsolete_entries_skipped() {
+        let po = "#~ msgid \"old\"\n#~ msgstr \"stare\"\n";
+        assert_eq!(checkable_ranges(po), CheckableRanges::default());
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let po =
+            "msgid \"x\"\nmsgstr \"%1$s a %-5.2f b %(name)s c %% d {0} e {{ f {not placeholder}\"";
+        assert_eq!(
+            checked_text(po, false),
+            vec![" a ", " b ", " c ", " d ", " e ", " f {not placeholder}"]
+        );
+    }
+
+    #[test]
+    fn test_escapes() {
+        let po = r#"msgid "x"
+msgstr "say \"hi\"\tnow\x41 b\101c"
+"#;
+        assert_eq!(
+            checked_text(po, false),
+            vec!["say ", "hi", "now", " b", "c"]
+        );
+    }
+}
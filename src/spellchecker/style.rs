@@ -0,0 +1,142 @@
+//! Style guide rules: words that must not be used even though they are spelled correctly.
+//!
+//! The forbidden words list holds one word per line. The replacements list maps a word to the
+//! preferred term, which may be more than one word:
+//! ```text
+//! e-mail email
+//! login log in
+//! ```
+//! Both lists are matched regardless of case, and lines starting with `#` are comments.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+
+use crate::prelude::*;
+
+/// What a style rule says about a word.
+#[derive(Debug, PartialEq)]
+pub enum StyleIssue<'a> {
+    /// The word must not be used
+    Forbidden,
+    /// The word should be replaced with the preferred term
+    Replace(&'a str),
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct StyleRules {
+    forbidden: HashSet<String>,
+    replacements: HashMap<String, String>,
+}
+
+impl StyleRules {
+    /// Loads the rules from the forbidden words list and the replacements list, either of which
+    /// may be missing.
+    pub fn load(forbidden: Option<&Path>, replacements: Option<&Path>) -> Result<Self> {
+        let read = |path: Option<&Path>| -> Result<String> {
+            match path {
+                Some(path) => fs::read_to_string(path)
+                    .with_context(|| format!("couldn't read {}", path.display())),
+                None => Ok(String::new()),
+            }
+        };
+        let name = |path: Option<&Path>| path.map(|path| path.display().to_string());
+
+        Self::from_sources(
+            &read(forbidden)?,
+            &read(replacements)?,
+            name(replacements).as_deref().unwrap_or_default(),
+        )
+    }
+
+    /// Parses the contents of the lists. `replacements_name` is the name errors in the
+    /// replacements list are reported under.
+    pub fn from_sources(
+        forbidden: &str,
+        replacements: &str,
+        replacements_name: &str,
+    ) -> Result<Self> {
+        let mut rules = StyleRules::default();
+
+        for word in rule_lines(forbidden).map(|(_, line)| line) {
+            rules.forbidden.insert(word.to_lowercase());
+        }
+
+        for (i, line) in rule_lines(replacements) {
+            match line.split_once(char::is_whitespace) {
+                Some((word, preferred)) => {
+                    rules
+                        .replacements
+                        .insert(word.to_lowercase(), preferred.trim().to_string());
+                }
+                None => {
+                    return Err(anyhow!(
+                        "{replacements_name}:{}: '{line}' is missing the preferred term",
+                        i + 1
+                    ));
+                }
+            }
+        }
+
+        Ok(rules)
+    }
+
+    /// Returns what the rules say about the word, if anything
+    pub fn check(&self, word: &str) -> Option<StyleIssue<'_>> {
+        let word = word.to_lowercase();
+        if let Some(preferred) = self.replacements.get(&word) {
+            return Some(StyleIssue::Replace(preferred));
+        }
+        self.forbidden
+            .contains(&word)
+            .then_some(StyleIssue::Forbidden)
+    }
+
+    /// Whether the word must not be used, either because it is forbidden or has a replacement
+    pub fn is_flagged(&self, word: &str) -> bool {
+        self.check(word).is_some()
+    }
+}
+
+/// The non-empty, non-comment lines of a list together with their indices
+fn rule_lines(source: &str) -> impl Iterator<Item = (usize, &str)> {
+    source
+        .lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules() {
+        let rules = StyleRules::from_sources(
+            "# Competitors\nAcmeSoft\n\n",
+            "e-mail email\nlogin   log in\n",
+            "replacements.txt",
+        )
+        .unwrap();
+
+        assert_eq!(rules.check("acmesoft"), Some(StyleIssue::Forbidden));
+        assert_eq!(rules.check("E-mail"), Some(StyleIssue::Replace("email")));
+        assert_eq!(rules.check("login"), Some(StyleIssue::Replace("log in")));
+        assert_eq!(rules.check("email"), None);
+        assert!(!rules.is_flagged("# Competitors"));
+    }
+
+    #[test]
+    fn test_missing_preferred_term() {
+        let error = StyleRules::from_sources("", "e-mail email\nlogin\n", "replacements.txt")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "replacements.txt:2: 'login' is missing the preferred term"
+        );
+    }
+}
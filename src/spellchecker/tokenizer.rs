@@ -0,0 +1,292 @@
+//! Splitting of document buffers into the words that should be spellchecked.
+//!
+//! Every document format (plain text, gettext translation files, ...) is handled by an
+//! implementation of [`Tokenizer`]. The formats are looked up by name or by file extension in a
+//! [`TokenizerRegistry`], to which library users can add their own implementations.
+
+use std::fmt::Debug;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::po::PoFile;
+
+/// Extracts the checkable words from a buffer of some document format.
+pub trait Tokenizer: Debug + Send + Sync {
+    /// Returns the byte ranges of the words in `buffer` that should be spellchecked. The ranges
+    /// must be sorted and must not overlap.
+    fn tokenize(&self, buffer: &str) -> Vec<Range<usize>>;
+
+    /// Returns the byte ranges of the words in `buffer` that are written in the source language of
+    /// the document rather than in its own, like the source strings of a translation file. They
+    /// are checked with the source language's dictionary. Sorted and not overlapping either, nor
+    /// overlapping the ranges of [`Tokenizer::tokenize`]. There are none by default.
+    fn tokenize_source(&self, _buffer: &str) -> Vec<Range<usize>> {
+        Vec::new()
+    }
+}
+
+/// The default tokenizer, which checks every word in the buffer.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PlainText;
+
+impl Tokenizer for PlainText {
+    fn tokenize(&self, buffer: &str) -> Vec<Range<usize>> {
+        #[allow(clippy::single_range_in_vec_init)]
+        words_in(buffer, &[0..buffer.len()])
+    }
+}
+
+/// Returns `true` if the passed character is considered one that separates words
+pub fn separates_word(character: &char) -> bool {
+    match character {
+        '.' | ':' | ',' | '!' | '?' | ';' | '&' => true,
+        c if c.is_whitespace() => true,
+        _ => false,
+    }
+}
+
+/// Splits the passed byte ranges of the buffer (the parts of a document that are text) into
+/// words. Words containing characters other than letters, apostrophes and hyphens, like numbers,
+/// are left out. Words never span multiple ranges - the end of a range always ends the word.
+///
+/// Hyphenated words like "e-mail" are kept whole in every format, so that the style rules can ban
+/// them, and it is up to the spellchecker to check their parts. Hyphens at the ends of a word are
+/// trimmed off.
+///
+/// This is what [`PlainText`] does for the whole buffer, and is meant to be reused by tokenizers
+/// which first have to find the text in a document.
+pub fn words_in(buffer: &str, ranges: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut words: Vec<Range<usize>> = Vec::new();
+
+    for range in ranges {
+        let mut is_proper_word: bool = true; // Whether the word contains any invalid characters
+                                             // like numbers
+
+        let mut start_pos: usize = range.start; // The position of the first character in the
+                                                // currently considered word
+
+        // A new line character is added to the back, so that the last word in the range is
+        // processed
+        for (i, c) in buffer[range.clone()]
+            .char_indices()
+            .map(|(i, c)| (i + range.start, c))
+            .chain([(range.end, '\n')])
+        {
+            if separates_word(&c) {
+                // Hyphens only join words, so they are trimmed from the ends (as in "pre- and
+                // post-war")
+                let text = buffer[start_pos..i].trim_end_matches('-');
+                let word = text.trim_start_matches('-');
+                if !word.is_empty() && is_proper_word {
+                    words.push(start_pos + text.len() - word.len()..start_pos + text.len());
+                }
+
+                // Reset variables
+                start_pos = i + c.len_utf8();
+                is_proper_word = true;
+                continue;
+            }
+
+            is_proper_word = is_proper_word
+                && (c.is_alphabetic() || c == '\u{0027}' || c == '\u{2019}' || c == '-');
+            // Allow apostrophes and hyphens inside of words
+        }
+    }
+
+    words
+}
+
+#[derive(Debug)]
+struct RegistryEntry {
+    name: String,
+    extensions: Vec<String>,
+    tokenizer: Arc<dyn Tokenizer>,
+}
+
+/// The set of available tokenizers, each with a name under which it can be selected, and the
+/// file extensions it is used for by default.
+#[derive(Debug)]
+pub struct TokenizerRegistry {
+    entries: Vec<RegistryEntry>,
+}
+
+impl Default for TokenizerRegistry {
+    /// Creates a registry with all of the built-in tokenizers
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register("plain", &["txt"], Arc::new(PlainText));
+        registry.register(
+            "po",
+            &["po", "pot"],
+            Arc::new(PoFile { check_msgid: false }),
+        );
+        registry.register("po-msgid", &[], Arc::new(PoFile { check_msgid: true }));
+        registry
+    }
+}
+
+impl TokenizerRegistry {
+    /// Creates an empty registry, without even the built-in tokenizers
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds a tokenizer under the given name, to be used for files with the given extensions
+    /// (without the leading dot). If a tokenizer with the same name already exists, it is
+    /// replaced. Extensions registered later take precedence over those registered earlier.
+    pub fn register(&mut self, name: &str, extensions: &[&str], tokenizer: Arc<dyn Tokenizer>) {
+        self.entries.retain(|entry| entry.name != name);
+        self.entries.push(RegistryEntry {
+            name: name.to_string(),
+            extensions: extensions.iter().map(|ext| ext.to_string()).collect(),
+            tokenizer,
+        });
+    }
+
+    /// Returns the tokenizer registered under `name`
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Tokenizer>> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| entry.tokenizer.clone())
+    }
+
+    /// Returns the name of the tokenizer registered for the extension of the file, if there is
+    /// one.
+    pub fn name_for_path(&self, path: &Path) -> Option<&str> {
+        let extension = path.extension()?.to_str()?;
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.extensions.iter().any(|ext| ext == extension))
+            .map(|entry| entry.name.as_str())
+    }
+
+    /// Returns the tokenizer registered for the extension of the file, falling back to
+    /// [`PlainText`] for unknown file types.
+    pub fn for_path(&self, path: &Path) -> Arc<dyn Tokenizer> {
+        self.name_for_path(path)
+            .and_then(|name| self.get(name))
+            .unwrap_or_else(|| Arc::new(PlainText))
+    }
+
+    /// The names of all registered tokenizers
+    pub fn names(&self) -> Vec<&str> {
+        self.entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words<'a>(buffer: &'a str, tokenizer: &dyn Tokenizer) -> Vec<&'a str> {
+        tokenizer
+            .tokenize(buffer)
+            .into_iter()
+            .map(|range| &buffer[range])
+            .collect()
+    }
+
+    #[test]
+    fn test_word_separate_test() {
+        assert!(separates_word(&'.'));
+        assert!(separates_word(&'&'));
+        assert!(!separates_word(&'"'));
+        assert!(!separates_word(&'\''));
+    }
+
+    #[test]
+    fn test_plain_text() {
+        assert_eq!(
+            words("Some text, with 12 numbers4 and apple's.", &PlainText),
+            vec!["Some", "text", "with", "and", "apple's"]
+        );
+        assert_eq!(words("  ", &PlainText), Vec::<&str>::new());
+        assert_eq!(
+            words("An e-mail, pre- and post-war -- ok", &PlainText),
+            vec!["An", "e-mail", "pre", "and", "post-war", "ok"]
+        );
+        assert_eq!(words("zażółć gęślą", &PlainText), vec!["zażółć", "gęślą"]);
+    }
+
+    #[test]
+    fn test_hyphens() {
+        // Words are split at the separators, and the ones with other characters are left out
+        assert_eq!(
+            words(
+                "Don't stop: it's 4pm, ok? Yes; tea & cake! \"Fine\" (really) x2",
+                &PlainText
+            ),
+            vec!["Don't", "stop", "it's", "ok", "Yes", "tea", "cake"]
+        );
+        assert_eq!(
+            words("l\u{2019}été, naïve café.", &PlainText),
+            vec!["l\u{2019}été", "naïve", "café"]
+        );
+
+        // Hyphenated words are kept whole, without the hyphens at their ends
+        assert_eq!(
+            words("-leading trailing- a--b --- utf-8 x-ray", &PlainText),
+            vec!["leading", "trailing", "a--b", "x-ray"]
+        );
+    }
+
+    #[test]
+    fn test_words_in_ranges() {
+        let text = "one two three four";
+        assert_eq!(
+            words_in(text, &[0..5, 8..16]),
+            vec![0..3, 4..5, 8..13, 14..16]
+        );
+    }
+
+    #[derive(Debug)]
+    struct FirstWord;
+
+    impl Tokenizer for FirstWord {
+        fn tokenize(&self, buffer: &str) -> Vec<Range<usize>> {
+            PlainText.tokenize(buffer).into_iter().take(1).collect()
+        }
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = TokenizerRegistry::default();
+        assert_eq!(
+            registry.name_for_path(Path::new("locale/pl.po")),
+            Some("po")
+        );
+        assert_eq!(
+            registry.name_for_path(Path::new("messages.pot")),
+            Some("po")
+        );
+        assert_eq!(
+            registry.name_for_path(Path::new("notes.txt")),
+            Some("plain")
+        );
+        assert_eq!(registry.name_for_path(Path::new("po")), None);
+        assert!(registry.get("po-msgid").is_some());
+        assert!(registry.get("markdown").is_none());
+
+        registry.register("first", &["txt"], Arc::new(FirstWord));
+        assert_eq!(
+            registry.name_for_path(Path::new("notes.txt")),
+            Some("first")
+        );
+        assert_eq!(
+            words("two words", registry.for_path(Path::new("a.txt")).as_ref()),
+            vec!["two"]
+        );
+        assert_eq!(
+            words("two words", registry.for_path(Path::new("a.md")).as_ref()),
+            vec!["two", "words"]
+        );
+    }
+}
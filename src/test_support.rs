@@ -0,0 +1,43 @@
+//! Helpers shared by the tests of the different modules.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::spellchecker::channel::ErrorModel;
+use crate::spellchecker::dictionary::Dictionary;
+use crate::spellchecker::Spellchecker;
+
+/// Creates a spellchecker with the word lists and the corrections corpus of the repository, and
+/// without the user's word list and correction history, so that the tests don't depend on what is
+/// installed on the machine running them.
+pub fn bundled_spellchecker() -> Spellchecker {
+    let dict = Dictionary::from_sources(
+        include_str!("../dict.txt"),
+        include_str!("../suggestion_dict.txt"),
+    )
+    .unwrap();
+    let corrections = Path::new(env!("CARGO_MANIFEST_DIR")).join("corrections.txt");
+
+    let mut spellchecker = Spellchecker::with_dictionary(dict);
+    spellchecker.set_error_model(ErrorModel::load(Some(&corrections)).unwrap());
+    spellchecker
+}
+
+/// Creates an empty directory named after the test in the system's temporary directory, removing
+/// what an earlier run may have left in it
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("spelchek-test-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Creates a small dictionary knowing "is", "some", "text" and "this", with "this" the most popular
+/// suggestion
+pub fn test_dictionary() -> Dictionary {
+    Dictionary::from_sources(
+        "is\nsome\ntext\nthis\n",
+        "is 10\nsome 10\ntext 10\nthis 20\n",
+    )
+    .unwrap()
+}
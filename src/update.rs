@@ -2,6 +2,7 @@ use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifier
 
 use crate::app_state::{AppState, Screen};
 use crate::prelude::*;
+use crate::spellchecker::layers::LayerKind;
 
 pub fn update(app: &mut AppState) -> Result<()> {
     app.suggest_selected();
@@ -23,7 +24,9 @@ pub fn update(app: &mut AppState) -> Result<()> {
             misspelling_selection(&key_event, app);
             suggestion_selection(&key_event, app);
             accept_suggestion(&key_event, app);
+            add_word(&key_event, app);
             save_file(&key_event, app)?;
+            file_selection(&key_event, app);
         }
         Screen::Quit => {
             quit_screen_input(&key_event, app)?;
@@ -79,6 +82,21 @@ fn accept_suggestion(key_event: &KeyEvent, app: &mut AppState) {
     }
 }
 
+/// Adds the selected misspelled word to a dictionary: on 'i' only for this session (ignoring it),
+/// on 'a' to the user's word list, and on 'p' to the project's word list.
+fn add_word(key_event: &KeyEvent, app: &mut AppState) {
+    if !key_event.modifiers.is_empty() {
+        return;
+    }
+
+    match key_event.code {
+        KeyCode::Char('i') => app.add_selected_word(LayerKind::Session),
+        KeyCode::Char('a') => app.add_selected_word(LayerKind::User),
+        KeyCode::Char('p') => app.add_selected_word(LayerKind::Project),
+        _ => {}
+    }
+}
+
 /// On 's' or 'S', save the corrected text to the file path from which it was first read. Returns
 /// `Result<()>` because it might fail upon file write failure
 fn save_file(key_event: &KeyEvent, app: &mut AppState) -> Result<()> {
@@ -90,10 +108,23 @@ fn save_file(key_event: &KeyEvent, app: &mut AppState) -> Result<()> {
     Ok(())
 }
 
+/// On ']' and '[', switch to the next and the previous file, when many files are checked. If the
+/// file can't be switched to, the error is shown in the status line.
+fn file_selection(key_event: &KeyEvent, app: &mut AppState) {
+    let selected = match key_event.code {
+        KeyCode::Char(']') => app.select_next_file(),
+        KeyCode::Char('[') => app.select_previous_file(),
+        _ => return,
+    };
+    if let Err(error) = selected {
+        app.status = Some(format!("error: {error:#}"));
+    }
+}
+
 fn quit_screen_input(key_event: &KeyEvent, app: &mut AppState) -> Result<()> {
     match key_event.code {
         KeyCode::Char(c) if c == 'y' || c == 'Y' => {
-            app.save_file()?;
+            app.save_all_files()?;
             app.quit();
         }
         KeyCode::Char(c) if c == 'n' || c == 'N' => {